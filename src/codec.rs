@@ -0,0 +1,67 @@
+use crate::error::Result;
+use crate::frame;
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Serialize};
+use std::io;
+
+/// Turns typed messages into the bytes sent over a pipe and back
+/// `Writer::write_with` sends one encoded message per writer session and
+/// `Reader::read_with` decodes everything read from one session
+pub trait Codec<T> {
+    fn encode(&self, value: &T) -> Result<Vec<u8>>;
+    fn decode(&self, data: &[u8]) -> Result<T>;
+}
+
+/// Encodes values as JSON
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonCodec;
+
+#[cfg(feature = "serde")]
+impl<T: Serialize + DeserializeOwned> Codec<T> for JsonCodec {
+    fn encode(&self, value: &T) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(value)?)
+    }
+    fn decode(&self, data: &[u8]) -> Result<T> {
+        Ok(serde_json::from_slice(data)?)
+    }
+}
+
+/// Puts the 4-byte big-endian length header of `Writer::write_frame_unversioned` in front of raw bytes
+/// Decoding fails with `InvalidData` unless the header matches the length of the payload
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LengthPrefix;
+
+impl Codec<Vec<u8>> for LengthPrefix {
+    fn encode(&self, value: &Vec<u8>) -> Result<Vec<u8>> {
+        Ok(frame::encode(value)?)
+    }
+    fn decode(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match data {
+            [a, b, c, d, payload @ ..]
+                if u32::from_be_bytes([*a, *b, *c, *d]) as usize == payload.len() =>
+            {
+                Ok(payload.to_vec())
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "length header doesn't match the frame",
+            )
+            .into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Codec, LengthPrefix};
+
+    #[test]
+    fn length_prefix_round_trip() {
+        let encoded = LengthPrefix.encode(&b"Hello pipe".to_vec()).unwrap();
+        assert_eq!(&encoded[..4], &[0, 0, 0, 10]);
+        assert_eq!(LengthPrefix.decode(&encoded).unwrap(), b"Hello pipe");
+        assert!(LengthPrefix.decode(&encoded[..8]).is_err());
+        assert!(LengthPrefix.decode(&[0, 0]).is_err());
+    }
+}
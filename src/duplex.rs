@@ -0,0 +1,84 @@
+use crate::error::Result;
+use crate::pipe::{ConnectedWriter, Pipe, Reader, Writer};
+use crate::stream::PipeReadStream;
+use std::path::Path;
+
+/// A request/response channel made of two named pipes
+/// One side reads `inbound` and writes `outbound`, its peer uses the same files the other way
+/// round, see `DuplexPipe::reverse`
+#[derive(Clone)]
+pub struct DuplexPipe {
+    inbound: Pipe,
+    outbound: Pipe,
+}
+
+impl DuplexPipe {
+    pub fn new(inbound: Pipe, outbound: Pipe) -> Self {
+        Self { inbound, outbound }
+    }
+    /// Create `<dir>/<name>.in` and `<dir>/<name>.out` if they don't exist yet
+    /// The returned side reads `<name>.in` and writes `<name>.out`, the peer should use `reverse`
+    pub fn pair(dir: &Path, name: &str) -> Result<Self> {
+        let inbound = Pipe::new(dir.join(format!("{}.in", name)));
+        let outbound = Pipe::new(dir.join(format!("{}.out", name)));
+        inbound.ensure_exists()?;
+        outbound.ensure_exists()?;
+        Ok(Self::new(inbound, outbound))
+    }
+    /// The same channel seen from the peer, reading what this side writes and the other way round
+    pub fn reverse(&self) -> Self {
+        Self::new(self.outbound.clone(), self.inbound.clone())
+    }
+    /// The pipe this side reads from
+    pub fn inbound(&self) -> &Pipe {
+        &self.inbound
+    }
+    /// The pipe this side writes to
+    pub fn outbound(&self) -> &Pipe {
+        &self.outbound
+    }
+    /// Create a reader for the inbound pipe
+    pub fn reader(&self) -> Reader {
+        self.inbound.reader()
+    }
+    /// Create a writer for the outbound pipe
+    pub fn writer(&self) -> Writer {
+        self.outbound.writer()
+    }
+    /// Open both directions, resolves once the peer has its read side open
+    /// The read side is opened first without blocking, so two peers connecting at once can't deadlock
+    pub async fn connect(&self) -> Result<(PipeReadStream, ConnectedWriter)> {
+        let reader = self.reader().open_stream()?;
+        let writer = self.writer().connect().await?;
+        Ok((reader, writer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use tokio::io::{self, AsyncBufReadExt, BufReader};
+    use tokio::task;
+
+    #[tokio::test]
+    async fn echo_server() -> io::Result<()> {
+        let server = super::DuplexPipe::pair(Path::new("/tmp"), "test_pipe_27").unwrap();
+        let client = server.reverse();
+        let t_server = task::spawn(async move {
+            let (reader, mut writer) = server.connect().await?;
+            let mut request = String::new();
+            BufReader::new(reader).read_line(&mut request).await?;
+            writer.write_str(&request).await?;
+            crate::error::Result::Ok(server)
+        });
+        let (reader, mut writer) = client.connect().await.unwrap();
+        writer.write_str("Hello pipe\n").await.unwrap();
+        let mut response = String::new();
+        BufReader::new(reader).read_line(&mut response).await?;
+        assert_eq!(response, "Hello pipe\n");
+        let server = t_server.await?.unwrap();
+        server.inbound().clone().delete().await?;
+        server.outbound().clone().delete().await?;
+        Ok(())
+    }
+}
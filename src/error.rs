@@ -0,0 +1,152 @@
+use std::{error, fmt, io, path::PathBuf, string::FromUtf8Error};
+
+/// Errors produced while working with Unix named pipes
+#[derive(Debug)]
+pub enum PipeError {
+    /// `mkfifo` failed to create the named pipe
+    #[cfg(unix)]
+    Mkfifo(nix::Error),
+    /// An I/O operation on the named pipe failed
+    Io(io::Error),
+    /// Opening, reading or writing the named pipe at `path` failed
+    PathIo {
+        /// What was being done, completes "failed to ... FIFO"
+        action: &'static str,
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// The path exists but is not a named pipe
+    NotAFifo(PathBuf),
+    /// A non-blocking open of the write side found no reader (`ENXIO`)
+    NoReader(PathBuf),
+    /// The writer sent more than the given number of bytes
+    LimitExceeded(usize),
+    /// The path can't be used, e.g. it contains an interior NUL byte or would leave the base
+    /// directory of a `PipeRegistry`
+    InvalidPath(PathBuf),
+    /// The deadline passed, holds the bytes read until then so the caller can resume
+    Timeout(Vec<u8>),
+    /// The CRC32 of a checked frame didn't match its payload
+    ChecksumMismatch { expected: u32, found: u32 },
+    /// A versioned frame carried a format version this build doesn't understand
+    UnsupportedVersion(u8),
+    /// A string read found invalid UTF-8, `into_bytes` hands back everything that was read
+    Utf8(FromUtf8Error),
+    /// A typed message could not be serialized or deserialized
+    #[cfg(feature = "serde")]
+    Json(serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, PipeError>;
+
+impl fmt::Display for PipeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(unix)]
+            PipeError::Mkfifo(err) => write!(f, "failed to create named pipe: {}", err),
+            PipeError::Io(err) => write!(f, "named pipe I/O error: {}", err),
+            PipeError::PathIo {
+                action,
+                path,
+                source,
+            } => write!(
+                f,
+                "failed to {} FIFO {}: {}",
+                action,
+                path.display(),
+                source
+            ),
+            PipeError::NotAFifo(path) => write!(f, "{} is not a named pipe", path.display()),
+            PipeError::NoReader(path) => write!(f, "no reader has {} open", path.display()),
+            PipeError::LimitExceeded(max) => write!(f, "named pipe sent more than {} bytes", max),
+            PipeError::InvalidPath(path) => {
+                write!(f, "{} is not a valid pipe path", path.display())
+            }
+            PipeError::Timeout(partial) if partial.is_empty() => write!(f, "named pipe timed out"),
+            PipeError::Timeout(partial) => {
+                write!(f, "named pipe timed out after {} bytes", partial.len())
+            }
+            PipeError::ChecksumMismatch { expected, found } => write!(
+                f,
+                "frame checksum mismatch: expected {:08x}, found {:08x}",
+                expected, found
+            ),
+            PipeError::UnsupportedVersion(version) => {
+                write!(f, "unsupported frame version {}", version)
+            }
+            PipeError::Utf8(err) => write!(f, "named pipe sent invalid UTF-8: {}", err),
+            #[cfg(feature = "serde")]
+            PipeError::Json(err) => write!(f, "invalid JSON message: {}", err),
+        }
+    }
+}
+
+impl error::Error for PipeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            #[cfg(unix)]
+            PipeError::Mkfifo(err) => Some(err),
+            PipeError::Io(err) => Some(err),
+            PipeError::PathIo { source, .. } => Some(source),
+            PipeError::Utf8(err) => Some(err),
+            PipeError::NotAFifo(_)
+            | PipeError::NoReader(_)
+            | PipeError::LimitExceeded(_)
+            | PipeError::InvalidPath(_)
+            | PipeError::Timeout(_)
+            | PipeError::ChecksumMismatch { .. }
+            | PipeError::UnsupportedVersion(_) => None,
+            #[cfg(feature = "serde")]
+            PipeError::Json(err) => Some(err),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl From<nix::Error> for PipeError {
+    fn from(err: nix::Error) -> Self {
+        PipeError::Mkfifo(err)
+    }
+}
+
+impl From<FromUtf8Error> for PipeError {
+    fn from(err: FromUtf8Error) -> Self {
+        PipeError::Utf8(err)
+    }
+}
+
+impl From<io::Error> for PipeError {
+    fn from(err: io::Error) -> Self {
+        PipeError::Io(err)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for PipeError {
+    fn from(err: serde_json::Error) -> Self {
+        PipeError::Json(err)
+    }
+}
+
+/// Lets `PipeError` flow back into code that only speaks `io::Error`
+impl From<PipeError> for io::Error {
+    fn from(err: PipeError) -> Self {
+        match err {
+            PipeError::Io(err) => err,
+            // Keep the kind callers match on, the message carries the path
+            PipeError::PathIo { ref source, .. } => io::Error::new(source.kind(), err),
+            #[cfg(unix)]
+            PipeError::Mkfifo(errno) => io::Error::from(errno),
+            PipeError::NotAFifo(_) => io::Error::new(io::ErrorKind::InvalidInput, err),
+            PipeError::NoReader(_) => io::Error::new(io::ErrorKind::NotConnected, err),
+            PipeError::LimitExceeded(_) => io::Error::new(io::ErrorKind::InvalidData, err),
+            PipeError::InvalidPath(_) => io::Error::new(io::ErrorKind::InvalidInput, err),
+            PipeError::Timeout(_) => io::Error::new(io::ErrorKind::TimedOut, err),
+            PipeError::ChecksumMismatch { .. } => io::Error::new(io::ErrorKind::InvalidData, err),
+            PipeError::UnsupportedVersion(_) => io::Error::new(io::ErrorKind::InvalidData, err),
+            PipeError::Utf8(_) => io::Error::new(io::ErrorKind::InvalidData, err),
+            #[cfg(feature = "serde")]
+            PipeError::Json(_) => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
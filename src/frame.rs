@@ -0,0 +1,329 @@
+use crate::error::{PipeError, Result};
+use std::io::{self, Read};
+#[cfg(feature = "async")]
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Size of the big-endian length header in front of every frame
+pub const HEADER_LEN: usize = 4;
+
+/// Largest payload a frame may carry
+/// A longer length header is taken for corruption or interleaved writes and refused before the
+/// reader allocates anything, since the CRC32 of a checked frame only covers the payload
+pub const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+/// Size of the big-endian CRC32 between the length header and the payload of a checked frame
+pub const CHECKSUM_LEN: usize = 4;
+
+/// Size of the big-endian channel id in front of the length header of a tagged frame
+pub const CHANNEL_LEN: usize = 2;
+
+/// Format version written in front of the length header of a versioned frame
+pub const VERSION: u8 = 1;
+
+/// Size of the version byte in front of the length header of a versioned frame
+pub const VERSION_LEN: usize = 1;
+
+/// Lookup table of the reflected CRC-32 (IEEE 802.3) polynomial
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// CRC-32 as used by zlib and Ethernet
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        CRC_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Prepend the length header to `data`, so the frame can be written in a single call
+pub(crate) fn encode(data: &[u8]) -> io::Result<Vec<u8>> {
+    if data.len() > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "frame payload exceeds MAX_FRAME_LEN",
+        ));
+    }
+    let len = data.len() as u32;
+    let mut frame = Vec::with_capacity(HEADER_LEN + data.len());
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend_from_slice(data);
+    Ok(frame)
+}
+
+/// Like `encode`, with the CRC32 of `data` between the header and the payload
+/// The header still holds the payload length only
+pub(crate) fn encode_checked(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut frame = encode(data)?;
+    frame.splice(HEADER_LEN..HEADER_LEN, crc32(data).to_be_bytes());
+    Ok(frame)
+}
+
+/// Like `encode`, with the channel id in front of the length header
+#[cfg_attr(windows, allow(dead_code))]
+pub(crate) fn encode_tagged(channel: u16, data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut frame = encode(data)?;
+    frame.splice(0..0, channel.to_be_bytes());
+    Ok(frame)
+}
+
+/// Like `encode`, with the format version in front of the length header
+pub(crate) fn encode_versioned(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut frame = encode(data)?;
+    frame.insert(0, VERSION);
+    Ok(frame)
+}
+
+fn check_version(version: u8) -> Result<()> {
+    if version == VERSION {
+        Ok(())
+    } else {
+        Err(PipeError::UnsupportedVersion(version))
+    }
+}
+
+/// Payload length announced by `header`, checked against `MAX_FRAME_LEN`
+fn payload_len(header: [u8; HEADER_LEN]) -> Result<usize> {
+    let len = u32::from_be_bytes(header) as usize;
+    if len > MAX_FRAME_LEN {
+        Err(PipeError::LimitExceeded(MAX_FRAME_LEN))
+    } else {
+        Ok(len)
+    }
+}
+
+fn verify(checksum: [u8; CHECKSUM_LEN], payload: Vec<u8>) -> Result<Vec<u8>> {
+    let expected = u32::from_be_bytes(checksum);
+    let found = crc32(&payload);
+    if expected == found {
+        Ok(payload)
+    } else {
+        Err(PipeError::ChecksumMismatch { expected, found })
+    }
+}
+
+/// Read one frame written with `encode_checked` and verify its checksum
+#[cfg_attr(windows, allow(dead_code))]
+pub(crate) fn read_checked<R: Read>(reader: &mut R) -> Result<Vec<u8>> {
+    let mut checksum = [0u8; CHECKSUM_LEN];
+    let mut header = [0u8; HEADER_LEN];
+    reader.read_exact(&mut header)?;
+    reader.read_exact(&mut checksum)?;
+    let mut payload = vec![0u8; payload_len(header)?];
+    reader.read_exact(&mut payload)?;
+    verify(checksum, payload)
+}
+
+/// Read one frame written with `encode_checked` from an async source and verify its checksum
+#[cfg(feature = "async")]
+pub(crate) async fn async_read_checked<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Vec<u8>> {
+    let mut checksum = [0u8; CHECKSUM_LEN];
+    let mut header = [0u8; HEADER_LEN];
+    reader.read_exact(&mut header).await?;
+    reader.read_exact(&mut checksum).await?;
+    let mut payload = vec![0u8; payload_len(header)?];
+    reader.read_exact(&mut payload).await?;
+    verify(checksum, payload)
+}
+
+/// Read one length-prefixed frame, looping until the header and the whole payload arrived
+#[cfg_attr(windows, allow(dead_code))]
+pub(crate) fn read<R: Read>(reader: &mut R) -> Result<Vec<u8>> {
+    let mut header = [0u8; HEADER_LEN];
+    reader.read_exact(&mut header)?;
+    let mut payload = vec![0u8; payload_len(header)?];
+    reader.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+/// Read one length-prefixed frame from an async source
+#[cfg(feature = "async")]
+pub(crate) async fn async_read<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Vec<u8>> {
+    let mut header = [0u8; HEADER_LEN];
+    reader.read_exact(&mut header).await?;
+    let mut payload = vec![0u8; payload_len(header)?];
+    reader.read_exact(&mut payload).await?;
+    Ok(payload)
+}
+
+/// Read one frame written with `encode_versioned`
+/// Fails with `PipeError::UnsupportedVersion` before touching the length header on a version mismatch
+#[cfg_attr(windows, allow(dead_code))]
+pub(crate) fn read_versioned<R: Read>(reader: &mut R) -> Result<Vec<u8>> {
+    let mut version = [0u8; VERSION_LEN];
+    reader.read_exact(&mut version)?;
+    check_version(version[0])?;
+    read(reader)
+}
+
+/// Read one frame written with `encode_versioned` from an async source
+#[cfg(feature = "async")]
+pub(crate) async fn async_read_versioned<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Vec<u8>> {
+    let mut version = [0u8; VERSION_LEN];
+    reader.read_exact(&mut version).await?;
+    check_version(version[0])?;
+    async_read(reader).await
+}
+
+/// Read one frame written with `encode_tagged` from an async source, with its channel id
+#[cfg(all(unix, feature = "async"))]
+pub(crate) async fn async_read_tagged<R: AsyncRead + Unpin>(
+    reader: &mut R,
+) -> Result<(u16, Vec<u8>)> {
+    let mut channel = [0u8; CHANNEL_LEN];
+    reader.read_exact(&mut channel).await?;
+    Ok((u16::from_be_bytes(channel), async_read(reader).await?))
+}
+
+/// Read one versioned frame into `buf` with its version byte and header, appending bytes as they arrive
+/// Dropping the future loses nothing, calling again with the same `buf` resumes the frame
+#[cfg(all(unix, feature = "async"))]
+pub(crate) async fn async_read_raw<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+) -> Result<()> {
+    read_up_to(reader, buf, VERSION_LEN).await?;
+    check_version(buf[0])?;
+    let start = VERSION_LEN + HEADER_LEN;
+    read_up_to(reader, buf, start).await?;
+    let len = payload_len([buf[1], buf[2], buf[3], buf[4]])?;
+    Ok(read_up_to(reader, buf, start + len).await?)
+}
+
+#[cfg(all(unix, feature = "async"))]
+async fn read_up_to<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+    len: usize,
+) -> io::Result<()> {
+    let mut chunk = [0u8; 4096];
+    while buf.len() < len {
+        let want = (len - buf.len()).min(chunk.len());
+        match reader.read(&mut chunk[..want]).await? {
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            read => buf.extend_from_slice(&chunk[..read]),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    /// Hands out a single byte per read call to exercise partial reads
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.0.is_empty() || buf.is_empty() {
+                return Ok(0);
+            }
+            buf[0] = self.0[0];
+            self.0 = &self.0[1..];
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn read_across_partial_header() {
+        let mut encoded = super::encode(b"first").unwrap();
+        encoded.extend(super::encode(b"second").unwrap());
+        let mut source = Trickle(&encoded);
+        assert_eq!(super::read(&mut source).unwrap(), b"first");
+        assert_eq!(super::read(&mut source).unwrap(), b"second");
+    }
+
+    #[test]
+    fn read_truncated_frame() {
+        use crate::error::PipeError;
+        let encoded = super::encode(b"Hello pipe").unwrap();
+        let mut source = Trickle(&encoded[..6]);
+        match super::read(&mut source) {
+            Err(PipeError::Io(err)) => assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof),
+            other => panic!("expected UnexpectedEof, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn corrupted_header_is_refused() {
+        use crate::error::PipeError;
+        let mut encoded = super::encode_checked(b"Hello pipe").unwrap();
+        // A length of ~4 GiB must fail before the payload buffer is allocated
+        encoded[0] = 0xFF;
+        assert!(matches!(
+            super::read_checked(&mut Trickle(&encoded)),
+            Err(PipeError::LimitExceeded(super::MAX_FRAME_LEN))
+        ));
+        let mut encoded = super::encode(b"Hello pipe").unwrap();
+        encoded[0] = 0xFF;
+        assert!(matches!(
+            super::read(&mut Trickle(&encoded)),
+            Err(PipeError::LimitExceeded(super::MAX_FRAME_LEN))
+        ));
+    }
+
+    #[test]
+    fn tagged_frame_layout() {
+        let encoded = super::encode_tagged(0x0102, b"data").unwrap();
+        assert_eq!(encoded, [1, 2, 0, 0, 0, 4, b'd', b'a', b't', b'a']);
+    }
+
+    #[test]
+    fn versioned_frame_rejects_unknown_version() {
+        use crate::error::PipeError;
+        let mut encoded = super::encode_versioned(b"data").unwrap();
+        assert_eq!(
+            encoded,
+            [super::VERSION, 0, 0, 0, 4, b'd', b'a', b't', b'a']
+        );
+        assert_eq!(
+            super::read_versioned(&mut Trickle(&encoded)).unwrap(),
+            b"data"
+        );
+        encoded[0] = super::VERSION + 1;
+        assert!(matches!(
+            super::read_versioned(&mut Trickle(&encoded)),
+            Err(PipeError::UnsupportedVersion(version)) if version == super::VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(super::crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(super::crc32(b""), 0);
+    }
+
+    #[test]
+    fn checked_frame_detects_corruption() {
+        use crate::error::PipeError;
+        let mut encoded = super::encode_checked(b"Hello pipe").unwrap();
+        assert_eq!(encoded.len(), 4 + 4 + 10);
+        assert_eq!(
+            super::read_checked(&mut Trickle(&encoded)).unwrap(),
+            b"Hello pipe"
+        );
+        encoded[12] ^= 0x20;
+        assert!(matches!(
+            super::read_checked(&mut Trickle(&encoded)),
+            Err(PipeError::ChecksumMismatch { .. })
+        ));
+    }
+}
@@ -1 +1,28 @@
+pub mod codec;
+#[cfg(all(unix, feature = "async"))]
+pub mod duplex;
+pub mod error;
+pub mod frame;
+#[cfg(all(unix, feature = "metrics"))]
+pub mod metrics;
+#[cfg(unix)]
 pub mod pipe;
+#[cfg(all(unix, any(feature = "async", feature = "async-std")))]
+mod runtime;
+#[cfg(all(unix, feature = "async"))]
+pub mod selector;
+#[cfg(unix)]
+mod signal;
+#[cfg(all(unix, feature = "async"))]
+pub mod stream;
+#[cfg(all(windows, feature = "async"))]
+pub mod windows;
+
+#[cfg(all(unix, feature = "async"))]
+pub use pipe::broadcast;
+#[cfg(unix)]
+pub use signal::ignore_sigpipe;
+
+/// On Windows the `pipe` module is backed by Windows named pipes, which need the `async` feature
+#[cfg(all(windows, feature = "async"))]
+pub use windows as pipe;
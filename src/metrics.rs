@@ -0,0 +1,54 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Point-in-time copy of the counters of a `PersistentReader` or `ConnectedWriter`
+/// Counters only ever grow, the difference between two snapshots is the traffic in between
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PipeMetrics {
+    /// Bytes handed to the pipe
+    pub bytes_written: u64,
+    /// Bytes taken out of the pipe, including headers of frames still sitting in a read buffer
+    pub bytes_read: u64,
+    /// Writes that failed
+    pub write_errors: u64,
+    /// Reads that failed
+    pub read_errors: u64,
+    /// Times the descriptor was replaced after the last writer left
+    pub reopens: u64,
+}
+
+/// Live counters behind a `PipeMetrics` snapshot, updated with relaxed atomics
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    bytes_written: AtomicU64,
+    bytes_read: AtomicU64,
+    write_errors: AtomicU64,
+    read_errors: AtomicU64,
+    reopens: AtomicU64,
+}
+
+impl Counters {
+    pub(crate) fn written(&self, len: usize) {
+        self.bytes_written.fetch_add(len as u64, Ordering::Relaxed);
+    }
+    pub(crate) fn read(&self, len: usize) {
+        self.bytes_read.fetch_add(len as u64, Ordering::Relaxed);
+    }
+    pub(crate) fn write_error(&self) {
+        self.write_errors.fetch_add(1, Ordering::Relaxed);
+    }
+    pub(crate) fn read_error(&self) {
+        self.read_errors.fetch_add(1, Ordering::Relaxed);
+    }
+    pub(crate) fn reopened(&self) {
+        self.reopens.fetch_add(1, Ordering::Relaxed);
+    }
+    pub(crate) fn snapshot(&self) -> PipeMetrics {
+        PipeMetrics {
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            write_errors: self.write_errors.load(Ordering::Relaxed),
+            read_errors: self.read_errors.load(Ordering::Relaxed),
+            reopens: self.reopens.load(Ordering::Relaxed),
+        }
+    }
+}
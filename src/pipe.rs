@@ -1,51 +1,602 @@
-use nix::{sys::stat::Mode, unistd};
+use crate::codec::Codec;
+use crate::error::{PipeError, Result};
+use crate::frame;
+#[cfg(feature = "metrics")]
+use crate::metrics::{Counters, PipeMetrics};
+#[cfg(any(feature = "async", feature = "async-std"))]
+use crate::runtime;
+use crate::signal;
+#[cfg(all(target_os = "linux", feature = "async"))]
+use crate::stream::{ConnectionEvent, PersistentReader};
+#[cfg(feature = "async")]
+use crate::stream::{PipeLines, PipeReadStream, PipeWriteStream, RecordReader};
+#[cfg(feature = "bytes")]
+use bytes::Bytes;
+#[cfg(feature = "async")]
+use futures::{stream, Stream};
+use nix::{
+    errno::Errno,
+    sys::stat::{self, Mode, SFlag},
+    unistd,
+};
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Serialize};
+#[cfg(feature = "async")]
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(not(feature = "async"))]
+use std::io;
 use std::io::prelude::*;
+use std::ops::Deref;
 use std::path::{Path, PathBuf};
-use tokio::{fs, io};
+use std::sync::Arc;
+#[cfg(feature = "async")]
+use std::time::Duration;
+#[cfg(feature = "async")]
+use tokio::sync::mpsc;
+#[cfg(feature = "async")]
+use tokio::{fs, io, time};
+#[cfg(feature = "tokio-util")]
+use tokio_util::sync::CancellationToken;
+
+/// Permission bits used when no mode is requested and no default was set
+/// `mkfifo` masks them with the process umask like any other new file
+pub const DEFAULT_MODE: u32 = 0o660;
+
+/// Process-wide mode for new pipes without an explicit mode, see `set_default_mode`
+static CURRENT_DEFAULT_MODE: std::sync::atomic::AtomicU32 =
+    std::sync::atomic::AtomicU32::new(DEFAULT_MODE);
+
+/// Replace `DEFAULT_MODE` for every pipe this process creates from now on without a mode
+/// The setting is an atomic and safe to change from any thread. Pipes already created and
+/// creations racing with the call may still use the previous mode
+pub fn set_default_mode(mode: Mode) {
+    // `mode_t` is only 16 bits wide on macOS
+    #[allow(clippy::useless_conversion)]
+    let bits = u32::from(mode.bits());
+    CURRENT_DEFAULT_MODE.store(bits, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// The mode new pipes without an explicit mode are created with
+pub fn default_mode() -> Mode {
+    Mode::from_bits_truncate(CURRENT_DEFAULT_MODE.load(std::sync::atomic::Ordering::Relaxed) as _)
+}
+
+/// Makes every temp pipe name created by this process unique
+static TEMP_COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+/// How often `Writer::connect_timeout` retries the open while it waits for a reader
+#[cfg(feature = "async")]
+const CONNECT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How often `ConnectedWriter::write_and_wait_drained` checks whether the pipe is empty
+#[cfg(feature = "async")]
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Smallest `PIPE_BUF` POSIX allows, used when the system doesn't report one
+const POSIX_PIPE_BUF: usize = 512;
 
 /// Create a new Unix named pipe on filesystem
+/// `mkfifo` masks the mode with the process umask, so an explicitly requested mode is set again
+/// afterwards. Changing the umask instead would race with other threads creating files
 fn create_pipe<P: ?Sized + nix::NixPath>(path: &P, mode: Option<Mode>) -> nix::Result<()> {
-    unistd::mkfifo(
-        path,
-        mode.unwrap_or_else(|| Mode::from_bits_truncate(0o660)),
-    )
+    unistd::mkfifo(path, mode.unwrap_or_else(default_mode))?;
+    if let Some(mode) = mode {
+        stat::fchmodat(None, path, mode, stat::FchmodatFlags::FollowSymlink)?;
+    }
+    Ok(())
+}
+
+/// Add the newline a line written to the pipe needs, so it goes out in the same write
+fn terminate_line(line: &str) -> std::borrow::Cow<'_, str> {
+    if line.ends_with('\n') {
+        line.into()
+    } else {
+        format!("{}\n", line).into()
+    }
+}
+
+/// Put `sep` after every record so the whole batch goes out in a single write
+fn join_records(records: &[&str], sep: u8) -> Vec<u8> {
+    let len = records.iter().map(|record| record.len() + 1).sum();
+    let mut data = Vec::with_capacity(len);
+    for record in records {
+        data.extend_from_slice(record.as_bytes());
+        data.push(sep);
+    }
+    data
+}
+
+nix::ioctl_read_bad!(fionread, nix::libc::FIONREAD, nix::libc::c_int);
+
+/// Number of bytes waiting in the pipe behind `fd`, without reading them
+fn bytes_readable(fd: std::os::unix::io::RawFd) -> std::io::Result<usize> {
+    let mut count: nix::libc::c_int = 0;
+    // SAFETY: FIONREAD only writes a single c_int through the pointer
+    unsafe { fionread(fd, &mut count) }.map_err(std::io::Error::from)?;
+    Ok(count as usize)
+}
+
+/// Keeps a descriptor in non-blocking mode and switches it back when dropped
+/// Restoring on drop covers a future that is cancelled half way, e.g. by `select!`
+#[cfg(feature = "async")]
+struct NonblockingGuard(std::os::unix::io::RawFd);
+
+#[cfg(feature = "async")]
+impl NonblockingGuard {
+    fn new(fd: std::os::unix::io::RawFd) -> io::Result<Self> {
+        set_nonblocking(fd, true)?;
+        Ok(Self(fd))
+    }
+    /// Switch back to blocking mode now, unlike the drop this reports a failure
+    fn restore(self) -> io::Result<()> {
+        let fd = self.0;
+        std::mem::forget(self);
+        set_nonblocking(fd, false)
+    }
+}
+
+#[cfg(feature = "async")]
+impl Drop for NonblockingGuard {
+    fn drop(&mut self) {
+        let _ = set_nonblocking(self.0, false);
+    }
+}
+
+/// Switch an already open descriptor in or out of non-blocking mode, `AsyncFd` requires it on
+pub(crate) fn set_nonblocking(fd: std::os::unix::io::RawFd, nonblocking: bool) -> io::Result<()> {
+    use nix::fcntl::{fcntl, FcntlArg, OFlag};
+    let flags = OFlag::from_bits_truncate(fcntl(fd, FcntlArg::F_GETFL)?);
+    let flags = if nonblocking {
+        flags | OFlag::O_NONBLOCK
+    } else {
+        flags - OFlag::O_NONBLOCK
+    };
+    fcntl(fd, FcntlArg::F_SETFL(flags))?;
+    Ok(())
+}
+
+/// A blocking read on a runtime thread stalls every task scheduled there until a writer shows up
+/// `spawn_blocking` threads belong to the runtime too, so they are reported as well
+#[cfg(all(feature = "tracing", feature = "async"))]
+fn warn_on_runtime(method: &str) {
+    if tokio::runtime::Handle::try_current().is_ok() {
+        tracing::warn!(
+            method,
+            "blocking named pipe read inside a tokio runtime, use the async variant instead"
+        );
+    }
+}
+
+/// Decode bytes read from the pipe, keeping the buffer if it is valid UTF-8 already
+fn lossy(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes)
+        .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned())
 }
 
 /// Delete a Unix named pipe from filesystem
+#[cfg(any(feature = "async", feature = "async-std"))]
 async fn remove_pipe<P: AsRef<Path>>(path: P) -> io::Result<()> {
-    fs::remove_file(&path).await
+    runtime::remove_file(path.as_ref()).await
+}
+
+/// Write `data` to every pipe at once and report the outcome per pipe, in the order given
+/// Only readers attached right now receive it, a pipe without one fails with
+/// `PipeError::NoReader` without holding up the others
+#[cfg(feature = "async")]
+pub async fn broadcast(pipes: &[Pipe], data: &[u8]) -> Vec<Result<()>> {
+    futures::future::join_all(
+        pipes
+            .iter()
+            .map(|pipe| async move { pipe.writer().write_connected(data).await }),
+    )
+    .await
 }
 
 /// This object represents a path to a Unix named pipe
+/// The path is shared, so cloning a `Pipe` or creating readers and writers doesn't allocate
 #[derive(Clone)]
 pub struct Pipe {
-    inner: PathBuf,
+    inner: Arc<Path>,
+    mode: Option<Mode>,
+    create_parents: bool,
+    cloexec: bool,
+    nonblocking: bool,
+    capacity: Option<usize>,
 }
 
 impl Pipe {
     pub fn new<T: Into<PathBuf>>(path: T) -> Self {
-        Self { inner: path.into() }
+        Self {
+            inner: Arc::from(path.into()),
+            mode: None,
+            create_parents: false,
+            cloexec: true,
+            nonblocking: false,
+            capacity: None,
+        }
+    }
+    /// Create a pipe that will be made with the given permission mode
+    pub fn with_mode<T: Into<PathBuf>>(path: T, mode: Mode) -> Self {
+        Self {
+            inner: Arc::from(path.into()),
+            mode: Some(mode),
+            create_parents: false,
+            cloexec: true,
+            nonblocking: false,
+            capacity: None,
+        }
+    }
+    /// Create a named pipe with a unique name in the system temp directory
+    /// That is `TMPDIR` if set and `/tmp` otherwise, see `new_temp_in` for the naming
+    pub fn new_temp() -> Result<Self> {
+        Self::new_temp_in(std::env::temp_dir())
+    }
+    /// Create a named pipe with a unique name in `dir`, e.g. `XDG_RUNTIME_DIR`
+    /// The name is built from the process id and a counter. Nothing removes the pipe on its own,
+    /// use `create_temp_guard` on the result to have it deleted on drop
+    pub fn new_temp_in<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        use std::sync::atomic::Ordering;
+        let dir = dir.as_ref();
+        loop {
+            let id = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+            let pipe = Pipe::new(dir.join(format!("fifo-{}-{}", std::process::id(), id)));
+            match create_pipe(&*pipe.inner, pipe.mode) {
+                Ok(()) => return Ok(pipe),
+                // Left behind by an earlier process with the same pid, try the next name
+                Err(Errno::EEXIST) => continue,
+                Err(errno) => return Err(errno.into()),
+            }
+        }
+    }
+    /// List the named pipes directly inside `dir`, sorted by path
+    /// Symlinks count if they point at a named pipe, everything else is skipped
+    pub fn scan_dir<P: AsRef<Path>>(dir: P) -> Result<Vec<Pipe>> {
+        use std::os::unix::fs::FileTypeExt;
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            match std::fs::metadata(&path) {
+                Ok(meta) if meta.file_type().is_fifo() => paths.push(path),
+                Ok(_) => {}
+                // Removed since read_dir listed it, or a dangling symlink
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        paths.sort();
+        Ok(paths.into_iter().map(Pipe::new).collect())
+    }
+    /// Start building a pipe with custom options
+    pub fn builder<T: Into<PathBuf>>(path: T) -> PipeBuilder {
+        PipeBuilder::new(path)
+    }
+    /// Path of the named pipe
+    pub fn path(&self) -> &Path {
+        &self.inner
     }
     /// Check if the path exists
     pub fn exists(&self) -> bool {
         self.inner.exists()
     }
+    /// Check if the path exists and is a named pipe, a missing path is not an error
+    pub fn is_fifo(&self) -> Result<bool> {
+        match stat::stat(&*self.inner) {
+            Ok(st) => Ok(SFlag::from_bits_truncate(st.st_mode) & SFlag::S_IFMT == SFlag::S_IFIFO),
+            Err(Errno::ENOENT) => Ok(false),
+            Err(errno) => Err(PipeError::Io(errno.into())),
+        }
+    }
     /// Make sure the path exists, otherwise create a named pipe in its place
-    pub fn ensure_exists(&self) -> nix::Result<()> {
+    /// Missing parent directories are only created if the `Pipe` was built with `create_parents`
+    /// Fails with `PipeError::NotAFifo` if something other than a named pipe occupies the path
+    pub fn ensure_exists(&self) -> Result<()> {
+        self.ensure(self.create_parents)
+    }
+    /// Same as `ensure_exists`, but always creates missing parent directories first
+    pub fn ensure_exists_all(&self) -> Result<()> {
+        self.ensure(true)
+    }
+    /// Same as `ensure_exists`, but runs on tokio's blocking pool
+    /// `ensure_exists`, `recreate`, `status` and the other non-async methods of `Pipe` make
+    /// blocking `stat`/`mkfifo` calls on the calling thread, `delete` and `try_delete` don't
+    #[cfg(feature = "async")]
+    pub async fn ensure_exists_async(&self) -> Result<()> {
+        let pipe = self.clone();
+        tokio::task::spawn_blocking(move || pipe.ensure_exists())
+            .await
+            .map_err(io::Error::from)?
+    }
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), fields(path = %self.inner.display()), err)
+    )]
+    fn ensure(&self, create_parents: bool) -> Result<()> {
+        self.check_path()?;
         if !self.exists() {
-            create_pipe(&self.inner, None)
-        } else {
+            if create_parents {
+                if let Some(parent) = self.inner.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+            }
+            create_pipe(&*self.inner, self.mode)?;
+            #[cfg(feature = "tracing")]
+            tracing::debug!(mode = ?self.mode, "created named pipe");
+            Ok(())
+        } else if self.is_fifo()? {
             Ok(())
+        } else {
+            Err(PipeError::NotAFifo(self.inner.to_path_buf()))
         }
     }
-    /// Try to delete the pipe from filesystem and consume the `NamedPipe`
-    pub async fn delete(self) -> io::Result<()> {
-        if self.inner.exists() {
-            remove_pipe(&self.inner).await
+    /// Replace the named pipe with a fresh one, made with `mode` or else the mode of the `Pipe`
+    /// The new pipe is created next to the old one and renamed over it, so the path is never
+    /// missing. Handles open on the old pipe stay connected to it, not to the new one.
+    /// Fails with `PipeError::NotAFifo` rather than replace anything that isn't a named pipe
+    pub fn recreate(&self, mode: Option<Mode>) -> Result<()> {
+        use std::sync::atomic::Ordering;
+        self.check_path()?;
+        if self.inner.symlink_metadata().is_ok() && !self.is_fifo()? {
+            return Err(PipeError::NotAFifo(self.inner.to_path_buf()));
+        }
+        let name = self
+            .inner
+            .file_name()
+            .ok_or_else(|| PipeError::InvalidPath(self.inner.to_path_buf()))?;
+        let mut tmp_name = std::ffi::OsString::from(".");
+        tmp_name.push(name);
+        tmp_name.push(format!(
+            ".{}-{}",
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let tmp = self.inner.with_file_name(tmp_name);
+        create_pipe(&tmp, mode.or(self.mode))?;
+        std::fs::rename(&tmp, &self.inner).map_err(|err| {
+            let _ = std::fs::remove_file(&tmp);
+            PipeError::from(err)
+        })
+    }
+    /// Move the named pipe to `new_path` and point this `Pipe` at it
+    /// Uses `rename`, which is atomic but only works within one filesystem, so stage the new pipe
+    /// next to the one it replaces. A named pipe already at `new_path` is replaced, open handles
+    /// stay connected to the moved pipe, while readers and writers made from this `Pipe` earlier
+    /// keep the old path. Fails with `PipeError::NotAFifo` rather than move anything that isn't a
+    /// named pipe, or replace anything at `new_path` that isn't one, symlinks included
+    pub fn rename<P: AsRef<Path>>(&mut self, new_path: P) -> Result<()> {
+        use std::os::unix::fs::FileTypeExt;
+        if !self.is_fifo()? {
+            return Err(PipeError::NotAFifo(self.inner.to_path_buf()));
+        }
+        let new_path = new_path.as_ref();
+        // Checked right before the call, a file created at `new_path` in between is still replaced
+        match new_path.symlink_metadata() {
+            Ok(meta) if !meta.file_type().is_fifo() => {
+                return Err(PipeError::NotAFifo(new_path.to_path_buf()))
+            }
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+            _ => {}
+        }
+        std::fs::rename(&self.inner, new_path).map_err(self.io_context("rename"))?;
+        self.inner = Arc::from(new_path);
+        Ok(())
+    }
+    /// Change the permissions of the existing named pipe to `mode` if they differ
+    /// `mkfifo` masks the requested mode with the process umask, so a pipe created with `0o660`
+    /// can end up as `0o640`. This fixes it in place without recreating the pipe
+    pub fn ensure_mode(&self, mode: Mode) -> Result<()> {
+        if !self.is_fifo()? {
+            return Err(PipeError::NotAFifo(self.inner.to_path_buf()));
+        }
+        let st = stat::stat(&*self.inner).map_err(std::io::Error::from)?;
+        if Mode::from_bits_truncate(st.st_mode) & Mode::all() != mode {
+            stat::fchmodat(None, &*self.inner, mode, stat::FchmodatFlags::FollowSymlink)
+                .map_err(std::io::Error::from)?;
+        }
+        Ok(())
+    }
+    /// Reject paths the OS can't represent before handing them to `mkfifo`
+    fn check_path(&self) -> Result<()> {
+        use std::os::unix::ffi::OsStrExt;
+        if self.inner.as_os_str().as_bytes().contains(&0) {
+            Err(PipeError::InvalidPath(self.inner.to_path_buf()))
         } else {
             Ok(())
         }
     }
+    /// Try to delete the pipe from filesystem and consume the `NamedPipe`
+    #[cfg(any(feature = "async", feature = "async-std"))]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), fields(path = %self.inner.display()), err)
+    )]
+    pub async fn delete(self) -> Result<()> {
+        self.try_delete().await.map(drop)
+    }
+    /// Try to delete the pipe from filesystem and consume the `NamedPipe`
+    /// Without the async features this is `delete_if_exists`, so a missing path is not an error and
+    /// anything other than a named pipe fails with `PipeError::NotAFifo`, same as the async version
+    #[cfg(not(any(feature = "async", feature = "async-std")))]
+    pub fn delete(self) -> Result<()> {
+        self.delete_if_exists()
+    }
+    /// Try to delete the pipe from filesystem without consuming the `Pipe`
+    /// Resolves to `false` if there was nothing to delete, so repeated calls don't fail.
+    /// Fails with `PipeError::NotAFifo` rather than remove anything that isn't a named pipe
+    #[cfg(any(feature = "async", feature = "async-std"))]
+    pub async fn try_delete(&self) -> Result<bool> {
+        use std::os::unix::fs::FileTypeExt;
+        let is_fifo = match runtime::metadata(&self.inner).await {
+            Ok(meta) => meta.file_type().is_fifo(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => false,
+            Err(err) => return Err(err.into()),
+        };
+        if !is_fifo {
+            return match runtime::symlink_metadata(&self.inner).await {
+                Ok(_) => Err(PipeError::NotAFifo(self.inner.to_path_buf())),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+                Err(err) => Err(err.into()),
+            };
+        }
+        match remove_pipe(&self.inner).await {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+    /// Delete the named pipe if it is there, without consuming the `Pipe`, no async
+    /// The `Pipe` stays usable, e.g. to `recreate` the pipe later. A missing path is not an error,
+    /// anything other than a named pipe fails with `PipeError::NotAFifo`
+    pub fn delete_if_exists(&self) -> Result<()> {
+        if !self.is_fifo()? {
+            return match self.inner.symlink_metadata() {
+                Ok(_) => Err(PipeError::NotAFifo(self.inner.to_path_buf())),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+                Err(err) => Err(err.into()),
+            };
+        }
+        match std::fs::remove_file(&self.inner) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+    /// Delete whatever file is at the path, named pipe or not, and consume the `Pipe`
+    /// Directories are never removed, a missing path is not an error
+    #[cfg(any(feature = "async", feature = "async-std"))]
+    pub async fn force_delete(self) -> Result<()> {
+        match remove_pipe(&self.inner).await {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Resize the kernel buffer of the pipe, returns the size actually granted
+    /// The kernel may round the size up, and only keeps it while some descriptor holds the pipe open
+    #[cfg(target_os = "linux")]
+    pub fn set_capacity(&self, size: usize) -> Result<usize> {
+        let size = i32::try_from(size)
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
+        self.pipe_fcntl(nix::fcntl::FcntlArg::F_SETPIPE_SZ(size))
+    }
+    /// Size of the kernel buffer of the pipe
+    /// Briefly opens the read side, see `status` for what that does to a writer waiting in `open`
+    #[cfg(target_os = "linux")]
+    pub fn capacity(&self) -> Result<usize> {
+        self.pipe_fcntl(nix::fcntl::FcntlArg::F_GETPIPE_SZ)
+    }
+    #[cfg(target_os = "linux")]
+    fn pipe_fcntl(&self, arg: nix::fcntl::FcntlArg) -> Result<usize> {
+        use std::os::unix::io::AsRawFd;
+        let file = self.open_query()?;
+        let size = nix::fcntl::fcntl(file.as_raw_fd(), arg).map_err(std::io::Error::from)?;
+        Ok(size as usize)
+    }
+    /// Open the read side just to query the pipe
+    /// With O_NONBLOCK the open doesn't wait for a writer. Any descriptor on the pipe counts as a
+    /// reader to the kernel, `O_RDWR` included, so there is no way to query it unnoticed
+    fn open_query(&self) -> std::io::Result<std::fs::File> {
+        use std::os::unix::fs::OpenOptionsExt;
+        std::fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(nix::fcntl::OFlag::O_NONBLOCK.bits())
+            .open(&self.inner)
+    }
+    /// Describe the pipe in one call, for health checks and admin tools
+    /// A missing path is not an error, it is reported with `exists: false`. For a named pipe the read
+    /// side is opened for the duration of the call: a writer blocked opening the pipe is let through
+    /// and, unless a real reader attaches meanwhile, its writes then fail with `EPIPE`
+    pub fn status(&self) -> Result<PipeStatus> {
+        use std::os::unix::io::AsRawFd;
+        let st = match stat::stat(&*self.inner) {
+            Ok(st) => Some(st),
+            Err(Errno::ENOENT) => None,
+            Err(errno) => return Err(PipeError::Io(errno.into())),
+        };
+        let is_fifo = st.is_some_and(|st| {
+            SFlag::from_bits_truncate(st.st_mode) & SFlag::S_IFMT == SFlag::S_IFIFO
+        });
+        // mode_t is narrower than u32 on macOS
+        #[allow(clippy::unnecessary_cast)]
+        let mode = st.map_or(0, |st| st.st_mode as u32 & 0o7777);
+        let mut status = PipeStatus {
+            exists: st.is_some(),
+            is_fifo,
+            mode,
+            #[cfg(target_os = "linux")]
+            capacity: None,
+            buffered: None,
+        };
+        if is_fifo {
+            let file = self.open_query()?;
+            #[cfg(target_os = "linux")]
+            {
+                let size = nix::fcntl::fcntl(file.as_raw_fd(), nix::fcntl::FcntlArg::F_GETPIPE_SZ)
+                    .map_err(std::io::Error::from)?;
+                status.capacity = Some(size as usize);
+            }
+            status.buffered = Some(bytes_readable(file.as_raw_fd())?);
+        }
+        Ok(status)
+    }
+    /// Largest write in bytes the system guarantees not to interleave with other writers
+    pub fn pipe_buf_size(&self) -> Result<usize> {
+        let size = unistd::pathconf(&*self.inner, unistd::PathconfVar::PIPE_BUF)
+            .map_err(std::io::Error::from)?;
+        Ok(size.map_or(POSIX_PIPE_BUF, |size| size as usize))
+    }
+    /// Attach the path of the pipe to an I/O error, `action` reads like "read from" or "open"
+    fn io_context(&self, action: &'static str) -> impl FnOnce(std::io::Error) -> PipeError + '_ {
+        move |source| PipeError::PathIo {
+            action,
+            path: self.inner.to_path_buf(),
+            source,
+        }
+    }
+    /// Like `io_context` for calls that already return a `PipeError`, only I/O failures get the path
+    fn error_context(&self, action: &'static str) -> impl FnOnce(PipeError) -> PipeError + '_ {
+        move |err| match err {
+            PipeError::Io(source) => self.io_context(action)(source),
+            err => err,
+        }
+    }
+    /// Apply the builder settings to a handle that outlives the call
+    /// Every descriptor is opened with `O_CLOEXEC`, it is cleared again if the `Pipe` was built with
+    /// `cloexec(false)`. With a `capacity` the kernel buffer is resized as well (Linux only)
+    fn configure_handle<T: std::os::unix::io::AsRawFd>(&self, handle: T) -> std::io::Result<T> {
+        use nix::fcntl::{fcntl, FcntlArg, FdFlag};
+        if !self.cloexec {
+            fcntl(handle.as_raw_fd(), FcntlArg::F_SETFD(FdFlag::empty()))?;
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(size) = self.capacity {
+            let size = i32::try_from(size)
+                .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
+            fcntl(handle.as_raw_fd(), FcntlArg::F_SETPIPE_SZ(size))?;
+        }
+        Ok(handle)
+    }
+    /// Create the named pipe if needed and open both ends of it in this process
+    /// Opening one end of a FIFO blocks until the other end is opened, so doing both from one
+    /// thread deadlocks with plain blocking opens. The reader is opened first with `O_RDWR`,
+    /// which never waits, and the write side then finds a reader already there. Since the
+    /// reader holds a write descriptor itself it never sees EOF, even once the writer is dropped.
+    /// Linux only, must be called from within a tokio runtime
+    #[cfg(all(target_os = "linux", feature = "async"))]
+    pub fn open_pair(&self) -> Result<(PersistentReader, ConnectedWriter)> {
+        self.ensure_exists()?;
+        let reader = self.reader().open_persistent()?;
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(&self.inner)
+            .map_err(self.io_context("open"))?;
+        let writer = ConnectedWriter::new(fs::File::from_std(self.configure_handle(file)?));
+        Ok((reader, writer))
+    }
+    /// Create the named pipe and return a guard that deletes it again when dropped
+    pub fn create_temp_guard(&self) -> Result<PipeGuard> {
+        self.ensure_exists()?;
+        Ok(PipeGuard { pipe: self.clone() })
+    }
 
     /// Create a reader for this named pipe
     pub fn reader(&self) -> Reader {
@@ -57,41 +608,920 @@ impl Pipe {
     }
 }
 
+impl fmt::Debug for Pipe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pipe")
+            .field("path", &self.inner)
+            .field("mode", &self.mode)
+            .finish()
+    }
+}
+
+impl fmt::Display for Pipe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.inner.display())
+    }
+}
+
+impl From<&str> for Pipe {
+    fn from(path: &str) -> Self {
+        Pipe::new(path)
+    }
+}
+
+impl From<String> for Pipe {
+    fn from(path: String) -> Self {
+        Pipe::new(path)
+    }
+}
+
+impl From<&Path> for Pipe {
+    fn from(path: &Path) -> Self {
+        Pipe::new(path)
+    }
+}
+
+impl From<PathBuf> for Pipe {
+    fn from(path: PathBuf) -> Self {
+        Pipe::new(path)
+    }
+}
+
+impl From<Pipe> for PathBuf {
+    fn from(pipe: Pipe) -> Self {
+        pipe.inner.to_path_buf()
+    }
+}
+
+impl AsRef<Path> for Pipe {
+    fn as_ref(&self) -> &Path {
+        &self.inner
+    }
+}
+
+/// A snapshot of the state of a named pipe, see `Pipe::status`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PipeStatus {
+    pub exists: bool,
+    pub is_fifo: bool,
+    /// Permission bits, 0 if the path doesn't exist
+    pub mode: u32,
+    /// Size of the kernel buffer, `None` unless the path is a named pipe
+    #[cfg(target_os = "linux")]
+    pub capacity: Option<usize>,
+    /// Bytes written but not read yet, `None` unless the path is a named pipe
+    pub buffered: Option<usize>,
+}
+
+/// Deletes the named pipe from filesystem when dropped
+pub struct PipeGuard {
+    pipe: Pipe,
+}
+
+impl Deref for PipeGuard {
+    type Target = Pipe;
+
+    fn deref(&self) -> &Pipe {
+        &self.pipe
+    }
+}
+
+impl Drop for PipeGuard {
+    /// `Drop` can't be async, so the pipe is removed with a blocking call on purpose
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.pipe.inner);
+    }
+}
+
+/// Collects the options used to create a `Pipe`
+pub struct PipeBuilder {
+    path: PathBuf,
+    mode: Option<Mode>,
+    create_parents: bool,
+    cloexec: bool,
+    nonblocking: bool,
+    capacity: Option<usize>,
+}
+
+impl PipeBuilder {
+    pub fn new<T: Into<PathBuf>>(path: T) -> Self {
+        Self {
+            path: path.into(),
+            mode: None,
+            create_parents: false,
+            cloexec: true,
+            nonblocking: false,
+            capacity: None,
+        }
+    }
+    /// Set the permission mode used when the named pipe is created
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+    /// Create missing parent directories when the named pipe is created
+    pub fn create_parents(mut self, create_parents: bool) -> Self {
+        self.create_parents = create_parents;
+        self
+    }
+    /// Whether long-lived handles (streams, `ConnectedWriter`, `PersistentReader`) are closed
+    /// when the process execs, defaults to `true`. Turn it off to let child processes inherit them,
+    /// one-shot reads and writes always use `O_CLOEXEC`
+    pub fn cloexec(mut self, cloexec: bool) -> Self {
+        self.cloexec = cloexec;
+        self
+    }
+    /// Whether the blocking handles from `Reader::open_blocking` and `Writer::connect_blocking`
+    /// start out in non-blocking mode, defaults to `false`. Their reads and writes then fail
+    /// with `WouldBlock` instead of waiting, same as after `set_nonblocking(true)`
+    pub fn nonblocking(mut self, nonblocking: bool) -> Self {
+        self.nonblocking = nonblocking;
+        self
+    }
+    /// Resize the kernel buffer to `size` bytes on every long-lived handle opened on the pipe
+    /// The kernel forgets the size once the last descriptor closes, so it is applied per handle
+    /// rather than once at creation, see `Pipe::set_capacity`. Ignored outside Linux
+    pub fn capacity(mut self, size: usize) -> Self {
+        self.capacity = Some(size);
+        self
+    }
+    /// Build the `Pipe`, nothing is created on filesystem yet
+    pub fn build(self) -> Pipe {
+        Pipe {
+            inner: Arc::from(self.path),
+            mode: self.mode,
+            create_parents: self.create_parents,
+            cloexec: self.cloexec,
+            nonblocking: self.nonblocking,
+            capacity: self.capacity,
+        }
+    }
+    /// Build the `Pipe` and create the named pipe on filesystem, see `Pipe::ensure_exists`
+    pub fn create(self) -> Result<Pipe> {
+        let pipe = self.build();
+        pipe.ensure_exists()?;
+        Ok(pipe)
+    }
+}
+
+/// Maps logical names to named pipes in one base directory
+/// Keeps the path conventions of an app with many pipes in one place
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PipeRegistry {
+    base: PathBuf,
+}
+
+impl PipeRegistry {
+    pub fn new<T: Into<PathBuf>>(base: T) -> Self {
+        Self { base: base.into() }
+    }
+    /// Directory the pipes live in
+    pub fn base(&self) -> &Path {
+        &self.base
+    }
+    /// The pipe named `name`, at `<base>/<name>`, nothing is created on filesystem yet
+    /// Fails with `PipeError::InvalidPath` unless `name` is a single plain path component, so
+    /// absolute names, `..` and nested paths can't leave `base`
+    pub fn get(&self, name: &str) -> Result<Pipe> {
+        use std::path::Component;
+        let mut components = Path::new(name).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => Ok(Pipe::new(self.base.join(name))),
+            _ => Err(PipeError::InvalidPath(PathBuf::from(name))),
+        }
+    }
+}
+
+/// How `Reader::open_with` opens the read side of the pipe
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpenMode {
+    /// Wait until a writer connects, the stream ends when the last writer disconnects
+    BlockingOnce,
+    /// Return right away even without a writer, the stream waits for data and ends when the
+    /// last writer that connected disconnects
+    NonBlocking,
+    /// Return right away and never end, writers may connect and disconnect any number of times.
+    /// Only available on Linux, see `Reader::open_persistent`
+    #[cfg(target_os = "linux")]
+    Persistent,
+}
+
+/// What `Reader::async_read_bounded` read, and which limit stopped it if any
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BoundedRead {
+    /// Everything read, never more than the requested maximum
+    pub data: Vec<u8>,
+    /// The writer sent more than the maximum, the rest is discarded when the pipe is closed and a
+    /// writer still sending may see `EPIPE`
+    pub truncated: bool,
+    /// The timeout passed before the writer closed the pipe
+    pub timed_out: bool,
+}
+
 /// An util wrapper for reading from Unix named pipes
 pub struct Reader {
     path: Pipe,
 }
 
 impl Reader {
-    /// Create a new reader, clone the specific Pipe
+    /// Create a new reader sharing the path of the specific Pipe
     pub fn from_path(source: &Pipe) -> Self {
         Self {
             path: source.clone(),
         }
     }
     /// Check if the named pipe actually exists, otherwise try to create it
-    pub fn pipe_exists(&self) -> nix::Result<&Self> {
+    /// with the mode the `Pipe` was built with
+    pub fn pipe_exists(&self) -> Result<&Self> {
         self.path.ensure_exists()?;
         Ok(self)
     }
     /// Read all bytes from the pipe no async
-    pub fn read(&self) -> std::io::Result<Vec<u8>> {
-        std::fs::read(&self.path.inner)
+    /// Blocks the thread until a writer has come and gone, so never call it on a tokio worker.
+    /// With the `tracing` feature a warning is logged when it runs inside a runtime
+    pub fn read(&self) -> Result<Vec<u8>> {
+        #[cfg(all(feature = "tracing", feature = "async"))]
+        warn_on_runtime("read");
+        self.read_file()
+    }
+    fn read_file(&self) -> Result<Vec<u8>> {
+        std::fs::read(&self.path.inner).map_err(self.path.io_context("read from"))
+    }
+    /// Run the blocking `read` on tokio's blocking pool
+    /// For callers who want the plain `std::fs::read` behaviour from async code
+    #[cfg(feature = "async")]
+    pub async fn async_read_blocking(&self) -> Result<Vec<u8>> {
+        let reader = self.path.reader();
+        tokio::task::spawn_blocking(move || reader.read_file())
+            .await
+            .map_err(io::Error::from)?
     }
     /// Read all bytes from the pipe
     /// The returned Future will resolve when something is written to the pipe
-    pub async fn async_read(&self) -> io::Result<Vec<u8>> {
-        fs::read(&self.path.inner).await
+    /// and the writer closes it, see `read_session`
+    #[cfg(any(feature = "async", feature = "async-std"))]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), fields(path = %self.path.inner.display()), err)
+    )]
+    pub async fn async_read(&self) -> Result<Vec<u8>> {
+        let data = runtime::read(&self.path.inner)
+            .await
+            .map_err(self.path.io_context("read from"))?;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(bytes = data.len(), "read from named pipe");
+        Ok(data)
+    }
+    /// Read all bytes from the pipe and append them to `buf` no async
+    /// Returns how many bytes were read. Clearing and reusing `buf` avoids a new allocation per read
+    pub fn read_into(&self, buf: &mut Vec<u8>) -> Result<usize> {
+        let mut file =
+            std::fs::File::open(&self.path.inner).map_err(self.path.io_context("open"))?;
+        file.read_to_end(buf)
+            .map_err(self.path.io_context("read from"))
+    }
+    /// Read all bytes from the pipe and append them to `buf`
+    /// Returns how many bytes were read. Clearing and reusing `buf` avoids a new allocation per read
+    #[cfg(feature = "async")]
+    pub async fn async_read_into(&self, buf: &mut Vec<u8>) -> Result<usize> {
+        use io::AsyncReadExt;
+        let mut file = fs::File::open(&self.path.inner)
+            .await
+            .map_err(self.path.io_context("open"))?;
+        file.read_to_end(buf)
+            .await
+            .map_err(self.path.io_context("read from"))
+    }
+    /// Read all bytes from the pipe, telling an empty session apart from data
+    /// Resolves to `None` if the writer closed the pipe without sending anything, and to
+    /// `Some` with everything it sent otherwise. Errors are still reported as errors
+    #[cfg(any(feature = "async", feature = "async-std"))]
+    pub async fn async_read_opt(&self) -> Result<Option<Vec<u8>>> {
+        let data = self.async_read().await?;
+        Ok(if data.is_empty() { None } else { Some(data) })
+    }
+    /// Read one writer session: wait for a writer to open the pipe, then read until it closes
+    /// The pipe is reopened on every call, so calling it again reads the next session.
+    /// Writers overlapping in time share one session, since the EOF only comes after the last
+    /// of them closes
+    #[cfg(any(feature = "async", feature = "async-std"))]
+    pub async fn read_session(&self) -> Result<Vec<u8>> {
+        self.async_read().await
+    }
+    /// Read exactly one message from a writer that opens the pipe, writes it and closes again
+    /// Consumes one open/close cycle, same as `read_session`. `messages` yields them one by one
+    #[cfg(any(feature = "async", feature = "async-std"))]
+    pub async fn read_message(&self) -> Result<Vec<u8>> {
+        self.read_session().await
+    }
+    /// A stream of the messages of successive writers, see `read_message`
+    /// Borrowing counterpart of `into_message_stream`, the pipe stays open between messages
+    #[cfg(feature = "async")]
+    pub fn messages(&self) -> impl Stream<Item = io::Result<Vec<u8>>> {
+        self.path.reader().into_message_stream()
+    }
+    /// Read all bytes from the pipe, but no more than `max`
+    /// Fails with `PipeError::LimitExceeded` as soon as the writer sends more
+    #[cfg(feature = "async")]
+    pub async fn async_read_capped(&self, max: usize) -> Result<Vec<u8>> {
+        let buffer = self.read_limited(max.saturating_add(1)).await?;
+        if buffer.len() > max {
+            Err(PipeError::LimitExceeded(max))
+        } else {
+            Ok(buffer)
+        }
+    }
+    /// Read at most `max` bytes from the pipe and drop the rest of the writer's data
+    #[cfg(feature = "async")]
+    pub async fn async_read_truncated(&self, max: usize) -> Result<Vec<u8>> {
+        self.read_limited(max).await
+    }
+    #[cfg(feature = "async")]
+    async fn read_limited(&self, limit: usize) -> Result<Vec<u8>> {
+        use io::AsyncReadExt;
+        let file = fs::File::open(&self.path.inner)
+            .await
+            .map_err(self.path.io_context("open"))?;
+        let mut buffer = Vec::new();
+        file.take(limit as u64)
+            .read_to_end(&mut buffer)
+            .await
+            .map_err(self.path.io_context("read from"))?;
+        Ok(buffer)
+    }
+    /// Read all bytes from the pipe, stopping at `max` bytes or once `timeout` has passed
+    /// Whatever was read until then is returned either way, `BoundedRead` tells which limit
+    /// was hit. For producers that can't be trusted with either memory or time
+    #[cfg(feature = "async")]
+    pub async fn async_read_bounded(&self, max: usize, timeout: Duration) -> Result<BoundedRead> {
+        use io::AsyncReadExt;
+        let mut stream = self.open_stream()?;
+        let mut data = Vec::new();
+        let read = async {
+            let mut chunk = [0u8; 4096];
+            // One byte past `max` tells a writer that sent exactly `max` from one that sent more
+            loop {
+                let want = (max.saturating_add(1) - data.len()).min(chunk.len());
+                match stream.read(&mut chunk[..want]).await? {
+                    0 => return io::Result::Ok(false),
+                    read => data.extend_from_slice(&chunk[..read]),
+                }
+                if data.len() > max {
+                    data.truncate(max);
+                    return Ok(true);
+                }
+            }
+        };
+        let (truncated, timed_out) = match time::timeout(timeout, read).await {
+            Ok(truncated) => (truncated.map_err(self.path.io_context("read from"))?, false),
+            Err(_elapsed) => (false, true),
+        };
+        Ok(BoundedRead {
+            data,
+            truncated,
+            timed_out,
+        })
+    }
+    /// Read all bytes from the pipe unless `token` is cancelled first
+    /// Resolves to `None` on cancellation, whatever was read until then is dropped. The pipe is
+    /// opened without blocking a thread, so a read with no writer in sight stops cleanly and
+    /// closes its descriptor. Must be called from within a tokio runtime
+    #[cfg(feature = "tokio-util")]
+    pub async fn async_read_cancellable(
+        &self,
+        token: CancellationToken,
+    ) -> Result<Option<Vec<u8>>> {
+        use io::AsyncReadExt;
+        let mut stream = self.open_stream()?;
+        let mut data = Vec::new();
+        let cancelled = tokio::select! {
+            biased;
+            _ = token.cancelled() => true,
+            read = stream.read_to_end(&mut data) => {
+                read.map_err(self.path.io_context("read from"))?;
+                false
+            }
+        };
+        Ok(if cancelled { None } else { Some(data) })
+    }
+    /// Read all bytes from the pipe into `Bytes` no async
+    /// The buffer read from the pipe is handed over without copying
+    #[cfg(feature = "bytes")]
+    pub fn read_bytes(&self) -> Result<Bytes> {
+        Ok(Bytes::from(self.read()?))
+    }
+    /// Read all bytes from the pipe into `Bytes`
+    /// The buffer read from the pipe is handed over without copying
+    #[cfg(all(feature = "bytes", any(feature = "async", feature = "async-std")))]
+    pub async fn async_read_bytes(&self) -> Result<Bytes> {
+        Ok(Bytes::from(self.async_read().await?))
     }
     /// Read a String from the pipe no async
-    /// The returned Future will resolve when something is written to the pipe
-    pub fn string(&self) -> std::io::Result<String> {
-        std::fs::read_to_string(&self.path.inner)
+    /// Fails with `PipeError::Utf8` holding the bytes read if they aren't valid UTF-8
+    pub fn string(&self) -> Result<String> {
+        #[cfg(all(feature = "tracing", feature = "async"))]
+        warn_on_runtime("string");
+        Ok(String::from_utf8(self.read_file()?)?)
     }
     /// Reads a String from the pipe.
-    /// The returned Future will resolve when something is written to the pipe
-    pub async fn async_read_str(&self) -> io::Result<String> {
-        fs::read_to_string(&self.path.inner).await
+    /// Fails with `PipeError::Utf8` holding the bytes read if they aren't valid UTF-8
+    #[cfg(any(feature = "async", feature = "async-std"))]
+    pub async fn async_read_str(&self) -> Result<String> {
+        Ok(String::from_utf8(self.async_read().await?)?)
+    }
+    /// Read a String from the pipe no async, replacing invalid UTF-8 with `U+FFFD`
+    pub fn read_lossy(&self) -> Result<String> {
+        Ok(lossy(self.read()?))
+    }
+    /// Read a String from the pipe, replacing invalid UTF-8 with `U+FFFD`
+    #[cfg(any(feature = "async", feature = "async-std"))]
+    pub async fn async_read_lossy(&self) -> Result<String> {
+        Ok(lossy(self.async_read().await?))
+    }
+    /// Open the read side and hand out the raw file, with `O_NONBLOCK` set if `nonblocking` is true
+    /// A blocking open waits until a writer opens the pipe. A non-blocking one returns at once,
+    /// reads then return 0 while no writer has the pipe open and fail with `WouldBlock` while
+    /// a writer has it open but sent nothing yet
+    pub fn open(&self, nonblocking: bool) -> Result<std::fs::File> {
+        use std::os::unix::fs::OpenOptionsExt;
+        let flags = if nonblocking {
+            nix::fcntl::OFlag::O_NONBLOCK
+        } else {
+            nix::fcntl::OFlag::empty()
+        };
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(flags.bits())
+            .open(&self.path.inner)
+            .map_err(self.path.io_context("open"))?;
+        Ok(self.path.configure_handle(file)?)
+    }
+    /// Open a stream that keeps the pipe open and implements `AsyncRead`
+    /// Must be called from within a tokio runtime
+    #[cfg(feature = "async")]
+    pub fn open_stream(&self) -> Result<PipeReadStream> {
+        let stream =
+            PipeReadStream::open(&self.path.inner).map_err(self.path.io_context("open"))?;
+        Ok(self.path.configure_handle(stream)?)
+    }
+    /// Open the pipe for blocking reads with `std::io::Read` and `BufRead`
+    /// Blocks until a writer opens the pipe, reads then return data as it arrives until EOF
+    pub fn open_blocking(&self) -> Result<SyncReadStream> {
+        use std::os::unix::io::AsRawFd;
+        let file = std::fs::File::open(&self.path.inner).map_err(self.path.io_context("open"))?;
+        if self.path.nonblocking {
+            set_nonblocking(file.as_raw_fd(), true)?;
+        }
+        Ok(SyncReadStream {
+            inner: std::io::BufReader::new(self.path.configure_handle(file)?),
+        })
+    }
+    /// Open a stream like `open_stream`, retrying up to `retries` times while the pipe is
+    /// missing or not accessible yet, waiting `backoff` between attempts
+    /// Smooths over a reader starting before the producer created the pipe, the last error is
+    /// returned once the retries are used up. Must be called from within a tokio runtime
+    #[cfg(feature = "async")]
+    pub async fn open_retry(&self, retries: usize, backoff: Duration) -> Result<PipeReadStream> {
+        let mut attempt = 0;
+        loop {
+            match self.open_stream() {
+                Err(PipeError::PathIo { source, .. })
+                    if attempt < retries
+                        && matches!(
+                            source.kind(),
+                            io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied
+                        ) =>
+                {
+                    attempt += 1;
+                    time::sleep(backoff).await;
+                }
+                result => return result,
+            }
+        }
+    }
+    /// Open a buffered stream whose internal read buffer holds `capacity` bytes
+    /// A larger buffer means fewer `read` calls on big transfers, `open_stream` wrapped in a plain
+    /// `BufReader` uses 8 KiB. Must be called from within a tokio runtime
+    #[cfg(feature = "async")]
+    pub fn open_stream_with_capacity(
+        &self,
+        capacity: usize,
+    ) -> Result<io::BufReader<PipeReadStream>> {
+        Ok(io::BufReader::with_capacity(capacity, self.open_stream()?))
+    }
+    /// Read exactly `buf.len()` bytes from the pipe no async
+    /// Fails with `UnexpectedEof` if the writer closes before the buffer is full
+    pub fn read_exact(&self, buf: &mut [u8]) -> Result<()> {
+        let mut file =
+            std::fs::File::open(&self.path.inner).map_err(self.path.io_context("open"))?;
+        file.read_exact(buf)
+            .map_err(self.path.io_context("read from"))
+    }
+    /// Read exactly `buf.len()` bytes from the pipe
+    /// Fails with `UnexpectedEof` if the writer closes before the buffer is full
+    #[cfg(feature = "async")]
+    pub async fn async_read_exact(&self, buf: &mut [u8]) -> Result<()> {
+        use io::AsyncReadExt;
+        let mut file = fs::File::open(&self.path.inner)
+            .await
+            .map_err(self.path.io_context("open"))?;
+        file.read_exact(buf)
+            .await
+            .map_err(self.path.io_context("read from"))?;
+        Ok(())
+    }
+    /// Copy everything from the pipe into `sink` until the writer closes
+    /// Returns the number of bytes copied, the data is streamed rather than buffered in full
+    #[cfg(feature = "async")]
+    pub async fn copy_to<W: io::AsyncWrite + Unpin>(&self, sink: W) -> Result<u64> {
+        self.copy_to_with_progress(sink, |_| {}).await
+    }
+    /// Same as `copy_to`, calling `progress` with the running total of bytes after every chunk
+    #[cfg(feature = "async")]
+    pub async fn copy_to_with_progress<W, F>(&self, mut sink: W, mut progress: F) -> Result<u64>
+    where
+        W: io::AsyncWrite + Unpin,
+        F: FnMut(u64),
+    {
+        use io::{AsyncReadExt, AsyncWriteExt};
+        let mut file = fs::File::open(&self.path.inner)
+            .await
+            .map_err(self.path.io_context("open"))?;
+        let mut buffer = vec![0u8; 8 * 1024];
+        let mut copied = 0u64;
+        loop {
+            let len = file
+                .read(&mut buffer)
+                .await
+                .map_err(self.path.io_context("read from"))?;
+            if len == 0 {
+                break;
+            }
+            sink.write_all(&buffer[..len]).await?;
+            copied += len as u64;
+            progress(copied);
+        }
+        sink.flush().await?;
+        Ok(copied)
+    }
+    /// Read the pipe line by line, keeping it open between lines
+    /// Must be called from within a tokio runtime
+    #[cfg(feature = "async")]
+    pub fn lines(&self) -> Result<PipeLines> {
+        Ok(PipeLines::new(io::BufReader::new(self.open_stream()?)))
+    }
+    /// Open the pipe for reading delimiter-separated records with `RecordReader::read_until`
+    /// Must be called from within a tokio runtime
+    #[cfg(feature = "async")]
+    pub fn record_reader(&self) -> Result<RecordReader> {
+        Ok(RecordReader::new(self.open_stream()?))
+    }
+    /// Turn the reader into a stream with one item per writer
+    /// Each item holds everything a writer sent between opening and closing the pipe, the EOF
+    /// after it closes delimits the message. A writer connecting before the reader drained the
+    /// previous one hides that EOF, so both end up in the same item.
+    /// The stream ends after yielding the first error
+    #[cfg(feature = "async")]
+    pub fn into_message_stream(self) -> impl Stream<Item = io::Result<Vec<u8>>> {
+        use io::AsyncReadExt;
+        stream::unfold(Some((self, None)), |state| async move {
+            let (reader, current): (Reader, Option<PipeReadStream>) = state?;
+            let message = async {
+                let mut current = match current {
+                    Some(current) => current,
+                    None => reader.open_stream()?,
+                };
+                let mut buffer = Vec::new();
+                current.read_to_end(&mut buffer).await?;
+                // Open the next stream before closing this one, so a writer connecting in
+                // between doesn't find the pipe without readers and lose its data
+                let next = reader.open_stream()?;
+                drop(current);
+                io::Result::Ok((buffer, next))
+            }
+            .await;
+            match message {
+                Ok((buffer, next)) => Some((Ok(buffer), Some((reader, Some(next))))),
+                Err(err) => Some((Err(err), None)),
+            }
+        })
+    }
+    /// Read a single versioned frame written by `Writer::write_frame` no async
+    /// Fails with `PipeError::UnsupportedVersion` if the writer used another frame format
+    pub fn read_frame(&self) -> Result<Vec<u8>> {
+        let mut file =
+            std::fs::File::open(&self.path.inner).map_err(self.path.io_context("open"))?;
+        frame::read_versioned(&mut file).map_err(self.path.error_context("read from"))
+    }
+    /// Read a single versioned frame written by `Writer::write_frame`
+    /// Fails with `PipeError::UnsupportedVersion` if the writer used another frame format
+    #[cfg(feature = "async")]
+    pub async fn async_read_frame(&self) -> Result<Vec<u8>> {
+        let mut file = fs::File::open(&self.path.inner)
+            .await
+            .map_err(self.path.io_context("open"))?;
+        frame::async_read_versioned(&mut file)
+            .await
+            .map_err(self.path.error_context("read from"))
+    }
+    /// Read a single length-prefixed frame written by `Writer::write_frame_unversioned` no async
+    pub fn read_frame_unversioned(&self) -> Result<Vec<u8>> {
+        let mut file =
+            std::fs::File::open(&self.path.inner).map_err(self.path.io_context("open"))?;
+        frame::read(&mut file).map_err(self.path.error_context("read from"))
+    }
+    /// Read a single length-prefixed frame written by `Writer::write_frame_unversioned`
+    #[cfg(feature = "async")]
+    pub async fn async_read_frame_unversioned(&self) -> Result<Vec<u8>> {
+        let mut file = fs::File::open(&self.path.inner)
+            .await
+            .map_err(self.path.io_context("open"))?;
+        frame::async_read(&mut file)
+            .await
+            .map_err(self.path.error_context("read from"))
+    }
+    /// Route frames written by `Writer::write_tagged` to one receiver per channel in `channels`
+    /// A background task reads the pipe until the last writer disconnects, frames for channels
+    /// that weren't asked for are dropped. The task stops early once every receiver is dropped,
+    /// or on a read error, which closes all receivers. Must be called from within a tokio runtime
+    #[cfg(feature = "async")]
+    pub fn demux(
+        &self,
+        channels: &[u16],
+    ) -> Result<HashMap<u16, mpsc::UnboundedReceiver<Vec<u8>>>> {
+        let mut stream = io::BufReader::new(self.open_stream()?);
+        let (senders, receivers): (HashMap<_, _>, HashMap<_, _>) = channels
+            .iter()
+            .map(|&channel| {
+                let (sender, receiver) = mpsc::unbounded_channel();
+                ((channel, sender), (channel, receiver))
+            })
+            .unzip();
+        tokio::spawn(async move {
+            while let Ok((channel, payload)) = frame::async_read_tagged(&mut stream).await {
+                if let Some(sender) = senders.get(&channel) {
+                    let _ = sender.send(payload);
+                }
+                if senders.values().all(|sender| sender.is_closed()) {
+                    break;
+                }
+            }
+        });
+        Ok(receivers)
+    }
+    /// Read a single frame written by `Writer::write_frame_checked` no async
+    /// Fails with `PipeError::ChecksumMismatch` if the payload doesn't match its CRC32
+    pub fn read_frame_checked(&self) -> Result<Vec<u8>> {
+        let mut file =
+            std::fs::File::open(&self.path.inner).map_err(self.path.io_context("open"))?;
+        frame::read_checked(&mut file).map_err(self.path.error_context("read from"))
+    }
+    /// Read a single frame written by `Writer::write_frame_checked`
+    /// Fails with `PipeError::ChecksumMismatch` if the payload doesn't match its CRC32
+    #[cfg(feature = "async")]
+    pub async fn async_read_frame_checked(&self) -> Result<Vec<u8>> {
+        let mut file = fs::File::open(&self.path.inner)
+            .await
+            .map_err(self.path.io_context("open"))?;
+        frame::async_read_checked(&mut file)
+            .await
+            .map_err(self.path.error_context("read from"))
+    }
+    /// Read a single versioned frame, giving up once `timeout` has passed
+    /// On timeout `PipeError::Timeout` holds the raw bytes read so far, version byte and length
+    /// header included, so nothing already taken from the kernel is lost
+    #[cfg(feature = "async")]
+    pub async fn read_frame_timeout(&self, timeout: Duration) -> Result<Vec<u8>> {
+        let mut stream = self.open_stream()?;
+        let mut data = Vec::new();
+        match time::timeout(timeout, frame::async_read_raw(&mut stream, &mut data)).await {
+            Ok(result) => {
+                result.map_err(self.path.error_context("read from"))?;
+                Ok(data.split_off(frame::VERSION_LEN + frame::HEADER_LEN))
+            }
+            Err(_elapsed) => Err(PipeError::Timeout(data)),
+        }
+    }
+    /// Read one JSON message written by `Writer::write_json` no async
+    #[cfg(feature = "serde")]
+    pub fn read_json<T: DeserializeOwned>(&self) -> Result<T> {
+        Ok(serde_json::from_slice(&self.read_frame()?)?)
+    }
+    /// Read one JSON message written by `Writer::write_json`
+    #[cfg(all(feature = "serde", feature = "async"))]
+    pub async fn async_read_json<T: DeserializeOwned>(&self) -> Result<T> {
+        Ok(serde_json::from_slice(&self.async_read_frame().await?)?)
+    }
+    /// Read everything one writer sends and decode it with `codec` no async
+    pub fn read_with<T, C: Codec<T>>(&self, codec: &C) -> Result<T> {
+        codec.decode(&self.read()?)
+    }
+    /// Read everything one writer sends and decode it with `codec`
+    #[cfg(any(feature = "async", feature = "async-std"))]
+    pub async fn async_read_with<T, C: Codec<T>>(&self, codec: &C) -> Result<T> {
+        codec.decode(&self.async_read().await?)
+    }
+    /// Open a reader that keeps working across many writers connecting and disconnecting
+    /// Only available on Linux, which defines opening a FIFO with `O_RDWR`
+    #[cfg(all(target_os = "linux", feature = "async"))]
+    pub fn open_persistent(&self) -> Result<PersistentReader> {
+        let stream = PipeReadStream::open_persistent(&self.path.inner)
+            .map_err(self.path.io_context("open"))?;
+        Ok(PersistentReader::new(self.path.configure_handle(stream)?))
+    }
+    /// Open a persistent reader that also reports writers attaching and leaving
+    /// Writers are only seen through the read side: `Connected` fires with the first data after
+    /// the pipe was idle and `Disconnected` once the last writer closed it, so events arrive as
+    /// the reader is read from. Instead of `O_RDWR` the pipe is reopened after every EOF
+    #[cfg(all(target_os = "linux", feature = "async"))]
+    pub fn open_persistent_with_events(
+        &self,
+    ) -> Result<(
+        PersistentReader,
+        tokio::sync::mpsc::UnboundedReceiver<ConnectionEvent>,
+    )> {
+        let (sender, events) = tokio::sync::mpsc::unbounded_channel();
+        let stream = PipeReadStream::open_watched(self.path.inner.clone(), sender)
+            .map_err(self.path.io_context("open"))?;
+        Ok((
+            PersistentReader::new(self.path.configure_handle(stream)?),
+            events,
+        ))
+    }
+    /// Open a stream on the read side with explicit connect and disconnect behavior
+    /// Must be called from within a tokio runtime
+    #[cfg(feature = "async")]
+    pub async fn open_with(&self, mode: OpenMode) -> Result<PipeReadStream> {
+        let stream = match mode {
+            OpenMode::BlockingOnce => {
+                let file = fs::File::open(&self.path.inner)
+                    .await
+                    .map_err(self.path.io_context("open"))?;
+                PipeReadStream::from_file(file.into_std().await)
+                    .map_err(self.path.io_context("open"))?
+            }
+            OpenMode::NonBlocking => {
+                PipeReadStream::open(&self.path.inner).map_err(self.path.io_context("open"))?
+            }
+            #[cfg(target_os = "linux")]
+            OpenMode::Persistent => PipeReadStream::open_persistent(&self.path.inner)
+                .map_err(self.path.io_context("open"))?,
+        };
+        Ok(self.path.configure_handle(stream)?)
+    }
+    /// Wait until a writer opens the pipe and return the connected read side
+    /// The stream is handed back instead of closed, since closing it would break the writer's pipe.
+    /// Fails with `PipeError::Timeout` if no writer shows up within `timeout`
+    #[cfg(feature = "async")]
+    pub async fn wait_for_writer(&self, timeout: Option<Duration>) -> Result<PipeReadStream> {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        let path = self.path.inner.clone();
+        // Claimed by whichever comes first, the blocking open or the timeout giving up on it
+        let claimed = Arc::new(AtomicBool::new(false));
+        let task_claimed = claimed.clone();
+        let mut open = tokio::task::spawn_blocking(move || {
+            if task_claimed.swap(true, Ordering::AcqRel) {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            std::fs::File::open(path)
+        });
+        let opened = match timeout {
+            None => open.await,
+            Some(timeout) => match time::timeout(timeout, &mut open).await {
+                Ok(opened) => opened,
+                Err(_elapsed) => {
+                    // Still queued on a busy blocking pool, the task won't open anything anymore
+                    if !claimed.swap(true, Ordering::AcqRel) {
+                        return Err(PipeError::Timeout(Vec::new()));
+                    }
+                    // The blocking open can't be cancelled, connect as a writer until it returns.
+                    // The probe only gets through once the task actually waits in `open`
+                    loop {
+                        let _ = self.writer_probe();
+                        if time::timeout(CONNECT_POLL_INTERVAL, &mut open)
+                            .await
+                            .is_ok()
+                        {
+                            return Err(PipeError::Timeout(Vec::new()));
+                        }
+                    }
+                }
+            },
+        };
+        let file = opened.map_err(io::Error::from)??;
+        Ok(self
+            .path
+            .configure_handle(PipeReadStream::from_file(file)?)?)
+    }
+    /// Briefly open the write side without blocking
+    #[cfg(feature = "async")]
+    fn writer_probe(&self) -> std::io::Result<std::fs::File> {
+        use std::os::unix::fs::OpenOptionsExt;
+        std::fs::OpenOptions::new()
+            .write(true)
+            .custom_flags(nix::fcntl::OFlag::O_NONBLOCK.bits())
+            .open(&self.path.inner)
+    }
+    /// Number of bytes buffered in the pipe right now, without reading them or waiting
+    /// The count is a snapshot, writers may add more before the next read. Briefly opens the read
+    /// side, see `Pipe::status` for what that does to a writer waiting in `open`
+    pub fn bytes_available(&self) -> Result<usize> {
+        use std::os::unix::io::AsRawFd;
+        let file = self
+            .path
+            .open_query()
+            .map_err(self.path.io_context("open"))?;
+        Ok(bytes_readable(file.as_raw_fd())?)
+    }
+    /// Wait until the pipe has data to read, without reading it
+    /// The pipe is only held open while waiting, so the data is kept for the next read as long as
+    /// a writer still has the pipe open. Otherwise use `open_stream` and `PipeReadStream::readable`
+    #[cfg(feature = "async")]
+    pub async fn readable(&self) -> Result<()> {
+        Ok(self.open_stream()?.readable().await?)
+    }
+    /// Throw away whatever is buffered in the pipe right now and return how many bytes were dropped
+    /// Never waits for new data, an empty pipe or one without writers drains to 0
+    #[cfg(feature = "async")]
+    pub async fn drain(&self) -> Result<usize> {
+        use std::os::unix::fs::OpenOptionsExt;
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(nix::fcntl::OFlag::O_NONBLOCK.bits())
+            .open(&self.path.inner)
+            .map_err(self.path.io_context("open"))?;
+        let mut buffer = [0u8; 4096];
+        let mut dropped = 0;
+        loop {
+            match file.read(&mut buffer) {
+                Ok(0) => return Ok(dropped),
+                Ok(len) => dropped += len,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(dropped),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(self.path.io_context("read from")(err)),
+            }
+        }
+    }
+    /// Read all bytes from the pipe, giving up after `timeout`
+    /// The timeout covers waiting for a writer and reading up to EOF, resolves to `None` when it elapses
+    #[cfg(feature = "async")]
+    pub async fn read_timeout(&self, timeout: Duration) -> Result<Option<Vec<u8>>> {
+        use io::AsyncReadExt;
+        let mut stream = self.open_stream()?;
+        let mut buffer = Vec::new();
+        match time::timeout(timeout, stream.read_to_end(&mut buffer)).await {
+            Ok(result) => {
+                result.map_err(self.path.io_context("read from"))?;
+                Ok(Some(buffer))
+            }
+            Err(_elapsed) => Ok(None),
+        }
+    }
+}
+
+impl fmt::Debug for Reader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Reader")
+            .field("path", &self.path.inner)
+            .field("exists", &self.path.exists())
+            .finish()
+    }
+}
+
+/// A blocking, buffered read handle on a Unix named pipe, closed on drop
+pub struct SyncReadStream {
+    inner: std::io::BufReader<std::fs::File>,
+}
+
+impl SyncReadStream {
+    /// Switch the descriptor in or out of non-blocking mode without reopening the pipe
+    /// Once non-blocking, a read with nothing buffered fails with `WouldBlock` instead of waiting
+    /// for data, and returns 0 like EOF while no writer has the pipe open
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        use std::os::unix::io::AsRawFd;
+        Ok(set_nonblocking(self.as_raw_fd(), nonblocking)?)
+    }
+}
+
+impl Read for SyncReadStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl BufRead for SyncReadStream {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt)
+    }
+}
+
+/// The descriptor is owned by the stream, callers must not close it
+impl std::os::unix::io::AsRawFd for SyncReadStream {
+    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        self.inner.get_ref().as_raw_fd()
     }
 }
 
@@ -101,52 +1531,733 @@ pub struct Writer {
 }
 
 impl Writer {
-    async fn _write(&self, data: &[u8]) -> io::Result<()> {
-        use io::AsyncWriteExt;
-        let mut file = fs::OpenOptions::new()
+    #[cfg(any(feature = "async", feature = "async-std"))]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip(self, data),
+            fields(path = %self.path.inner.display(), bytes = data.len()),
+            err
+        )
+    )]
+    async fn _write(&self, data: &[u8]) -> Result<()> {
+        use runtime::WriteExt;
+        let mut file = runtime::open_write(&self.path.inner)
+            .await
+            .map_err(self.path.io_context("open"))?;
+        #[cfg(feature = "tracing")]
+        tracing::trace!("opened named pipe for writing");
+        file.write_all(data)
+            .await
+            .map_err(self.path.io_context("write to"))?;
+        // Both runtimes complete file writes in the background, wait so errors surface here
+        file.flush().await.map_err(self.path.io_context("write to"))
+    }
+    fn _write_blocking(&self, data: &[u8]) -> Result<()> {
+        let mut file = std::fs::OpenOptions::new()
             .write(true)
             .create(false)
             .open(&self.path.inner)
-            .await?;
-        file.write_all(data).await
+            .map_err(self.path.io_context("open"))?;
+        file.write_all(data)
+            .map_err(self.path.io_context("write to"))
     }
+    /// Create a new writer sharing the path of the specific Pipe
+    /// The first writer makes the process ignore SIGPIPE, see `ignore_sigpipe`
     pub fn from_path(source: &Pipe) -> Self {
+        signal::ignore_sigpipe();
         Self {
             path: source.clone(),
         }
     }
     /// Check if the named pipe actually exists, otherwise try to create it
-    pub fn pipe_exists(&self) -> nix::Result<&Self> {
+    /// with the mode the `Pipe` was built with
+    pub fn pipe_exists(&self) -> Result<&Self> {
         self.path.ensure_exists()?;
         Ok(self)
     }
+    /// Write byte data to the pipe no async
+    /// Opens the pipe without truncating, same as `async_write`, so consecutive writes append
+    pub fn write(&self, data: &[u8]) -> Result<()> {
+        self._write_blocking(data)
+    }
     /// Write byte data to the pipe
-    pub fn write(&self, data: &[u8]) -> std::io::Result<()> {
-        let mut buffer = std::fs::File::create(&self.path.inner.to_str().unwrap())?;
-        buffer.write_all(data)?;
+    #[cfg(any(feature = "async", feature = "async-std"))]
+    pub async fn async_write(&self, data: &[u8]) -> Result<()> {
+        self._write(data).await
+    }
+    /// Write `Bytes` to the pipe no async
+    #[cfg(feature = "bytes")]
+    pub fn write_bytes(&self, data: Bytes) -> Result<()> {
+        self.write(&data)
+    }
+    /// Write `Bytes` to the pipe
+    #[cfg(all(feature = "bytes", any(feature = "async", feature = "async-std")))]
+    pub async fn async_write_bytes(&self, data: Bytes) -> Result<()> {
+        self.async_write(&data).await
+    }
+    /// Write &str data to the pipe no async
+    /// Opens the pipe without truncating, same as `async_write_str`, so consecutive writes append
+    pub fn write_str(&self, data: String) -> Result<()> {
+        self._write_blocking(data.as_bytes())
+    }
+    /// Write &str data to the pipe
+    #[cfg(any(feature = "async", feature = "async-std"))]
+    pub async fn async_write_str(&self, data: &str) -> Result<()> {
+        self._write(data.as_bytes()).await
+    }
+    /// Write one line of text to the pipe no async
+    /// A trailing `\n` is appended unless `line` already ends with one
+    pub fn write_line(&self, line: &str) -> Result<()> {
+        self._write_blocking(terminate_line(line).as_bytes())
+    }
+    /// Write one line of text to the pipe
+    /// A trailing `\n` is appended unless `line` already ends with one
+    #[cfg(any(feature = "async", feature = "async-std"))]
+    pub async fn async_write_line(&self, line: &str) -> Result<()> {
+        self._write(terminate_line(line).as_bytes()).await
+    }
+    /// Write a batch of records to the pipe, each one followed by `sep`, no async
+    /// The batch is handed to the kernel in one `write_all`, which is only atomic with respect to
+    /// other writers while it stays within `Pipe::pipe_buf_size`. Larger batches may interleave
+    pub fn write_records(&self, records: &[&str], sep: u8) -> Result<()> {
+        self._write_blocking(&join_records(records, sep))
+    }
+    /// Write a batch of records to the pipe, each one followed by `sep`
+    /// The batch is handed to the kernel in one `write_all`, which is only atomic with respect to
+    /// other writers while it stays within `Pipe::pipe_buf_size`. Larger batches may interleave
+    #[cfg(any(feature = "async", feature = "async-std"))]
+    pub async fn async_write_records(&self, records: &[&str], sep: u8) -> Result<()> {
+        self._write(&join_records(records, sep)).await
+    }
+    /// Write byte data with a single `write` call no async
+    /// Returns how many bytes the kernel accepted, which may be less than `data.len()`
+    pub fn write_partial(&self, data: &[u8]) -> Result<usize> {
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(false)
+            .open(&self.path.inner)
+            .map_err(self.path.io_context("open"))?;
+        file.write(data).map_err(self.path.io_context("write to"))
+    }
+    /// Write byte data with a single `write` call
+    /// Returns how many bytes the kernel accepted, which may be less than `data.len()`. Waits for
+    /// a reader and then for room in the pipe, the write itself is non-blocking and takes what fits
+    #[cfg(feature = "async")]
+    pub async fn async_write_partial(&self, data: &[u8]) -> Result<usize> {
+        use io::AsyncWriteExt;
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create(false)
+            .open(&self.path.inner)
+            .await
+            .map_err(self.path.io_context("open"))?;
+        // tokio's File reports the length it buffered, not what the kernel took, so write
+        // through a readiness-based stream instead
+        let mut stream = PipeWriteStream::from_file(file.into_std().await)
+            .map_err(self.path.io_context("open"))?;
+        stream
+            .write(data)
+            .await
+            .map_err(self.path.io_context("write to"))
+    }
+    /// Check that `data` fits in a single atomic write
+    fn check_atomic(&self, data: &[u8]) -> Result<()> {
+        let limit = self.path.pipe_buf_size()?;
+        if data.len() > limit {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} bytes exceed PIPE_BUF ({} bytes), the write could interleave with other writers",
+                    data.len(),
+                    limit
+                ),
+            )
+            .into());
+        }
+        Ok(())
+    }
+    /// The kernel won't split a write of at most `PIPE_BUF` bytes, so a short count means something broke
+    fn check_written(data: &[u8], written: usize) -> Result<()> {
+        if written == data.len() {
+            Ok(())
+        } else {
+            Err(io::Error::from(io::ErrorKind::WriteZero).into())
+        }
+    }
+    /// Write byte data in one piece that never interleaves with other writers no async
+    /// Fails with `InvalidInput` if `data` is larger than `Pipe::pipe_buf_size`
+    pub fn write_atomic(&self, data: &[u8]) -> Result<()> {
+        self.check_atomic(data)?;
+        Self::check_written(data, self.write_partial(data)?)
+    }
+    /// Write byte data in one piece that never interleaves with other writers
+    /// Fails with `InvalidInput` if `data` is larger than `Pipe::pipe_buf_size`
+    #[cfg(feature = "async")]
+    pub async fn async_write_atomic(&self, data: &[u8]) -> Result<()> {
+        self.check_atomic(data)?;
+        Self::check_written(data, self.async_write_partial(data).await?)
+    }
+    /// Write a large payload in pieces of at most `chunk_size` bytes over one open descriptor
+    /// The task yields to the scheduler after every chunk, so a slow reader doesn't keep other
+    /// tasks from running. That costs a bit of throughput compared to `async_write`
+    #[cfg(feature = "async")]
+    pub async fn write_chunked(&self, data: &[u8], chunk_size: usize) -> Result<()> {
+        if chunk_size == 0 {
+            return Err(
+                io::Error::new(io::ErrorKind::InvalidInput, "chunk size must not be zero").into(),
+            );
+        }
+        let mut connected = self.connect().await?;
+        for chunk in data.chunks(chunk_size) {
+            connected.write(chunk).await?;
+            tokio::task::yield_now().await;
+        }
         Ok(())
     }
+    /// Write byte data, retrying up to `retries` times when no reader is attached or the reader
+    /// goes away mid-write, waiting `backoff` between attempts. A retry resends all of `data`,
+    /// so a reader that disconnected mid-write may have seen part of it already.
+    #[cfg(feature = "async")]
+    pub async fn write_retry(&self, data: &[u8], retries: usize, backoff: Duration) -> Result<()> {
+        let mut attempt = 0;
+        loop {
+            match self.write_connected(data).await {
+                Err(err) if attempt < retries && Self::is_disconnect(&err) => {
+                    attempt += 1;
+                    time::sleep(backoff).await;
+                }
+                result => return result,
+            }
+        }
+    }
+    /// Write to a reader that is attached right now, without waiting for one
+    #[cfg(feature = "async")]
+    async fn write_connected(&self, data: &[u8]) -> Result<()> {
+        use io::AsyncWriteExt;
+        let mut stream = self.open_stream()?;
+        stream
+            .write_all(data)
+            .await
+            .map_err(self.path.io_context("write to"))?;
+        stream
+            .shutdown()
+            .await
+            .map_err(self.path.io_context("write to"))
+    }
+    #[cfg(feature = "async")]
+    fn is_disconnect(err: &PipeError) -> bool {
+        match err {
+            PipeError::NoReader(_) => true,
+            PipeError::Io(err) | PipeError::PathIo { source: err, .. } => {
+                err.kind() == io::ErrorKind::BrokenPipe
+            }
+            _ => false,
+        }
+    }
+    /// Copy everything from `source` into the pipe over one open descriptor
+    /// Returns the number of bytes copied, the data is streamed rather than buffered in full
+    #[cfg(feature = "async")]
+    pub async fn write_from<R: io::AsyncRead + Unpin>(&self, mut source: R) -> Result<u64> {
+        use io::{AsyncReadExt, AsyncWriteExt};
+        let mut connected = self.connect().await?;
+        let mut buffer = vec![0u8; 8 * 1024];
+        let mut copied = 0u64;
+        loop {
+            let len = source.read(&mut buffer).await?;
+            if len == 0 {
+                break;
+            }
+            connected
+                .file
+                .write_all(&buffer[..len])
+                .await
+                .map_err(self.path.io_context("write to"))?;
+            copied += len as u64;
+        }
+        connected
+            .file
+            .flush()
+            .await
+            .map_err(self.path.io_context("write to"))?;
+        Ok(copied)
+    }
+    /// Write data as one frame with a version byte and a 4-byte big-endian length header no async
+    pub fn write_frame(&self, data: &[u8]) -> Result<()> {
+        self._write_blocking(&frame::encode_versioned(data)?)
+    }
+    /// Write data as one frame with a version byte and a 4-byte big-endian length header
+    #[cfg(any(feature = "async", feature = "async-std"))]
+    pub async fn async_write_frame(&self, data: &[u8]) -> Result<()> {
+        self._write(&frame::encode_versioned(data)?).await
+    }
+    /// Write data as one frame with only a 4-byte big-endian length header no async
+    /// For readers that predate versioned frames
+    pub fn write_frame_unversioned(&self, data: &[u8]) -> Result<()> {
+        self._write_blocking(&frame::encode(data)?)
+    }
+    /// Write data as one frame with only a 4-byte big-endian length header
+    /// For readers that predate versioned frames
+    #[cfg(any(feature = "async", feature = "async-std"))]
+    pub async fn async_write_frame_unversioned(&self, data: &[u8]) -> Result<()> {
+        self._write(&frame::encode(data)?).await
+    }
+    /// Write data as one frame tagged with `channel`, for a reader split up with `Reader::demux`
+    /// no async
+    pub fn write_tagged(&self, channel: u16, data: &[u8]) -> Result<()> {
+        self._write_blocking(&frame::encode_tagged(channel, data)?)
+    }
+    /// Write data as one frame tagged with `channel`, for a reader split up with `Reader::demux`
+    #[cfg(any(feature = "async", feature = "async-std"))]
+    pub async fn async_write_tagged(&self, channel: u16, data: &[u8]) -> Result<()> {
+        self._write(&frame::encode_tagged(channel, data)?).await
+    }
+    /// Write data as one frame with a length header and a CRC32 of the payload no async
+    pub fn write_frame_checked(&self, data: &[u8]) -> Result<()> {
+        self._write_blocking(&frame::encode_checked(data)?)
+    }
+    /// Write data as one frame with a length header and a CRC32 of the payload
+    #[cfg(any(feature = "async", feature = "async-std"))]
+    pub async fn async_write_frame_checked(&self, data: &[u8]) -> Result<()> {
+        self._write(&frame::encode_checked(data)?).await
+    }
+    /// Serialize a value to JSON and write it as one frame no async
+    #[cfg(feature = "serde")]
+    pub fn write_json<T: Serialize>(&self, value: &T) -> Result<()> {
+        self.write_frame(&serde_json::to_vec(value)?)
+    }
+    /// Serialize a value to JSON and write it as one frame
+    #[cfg(all(feature = "serde", any(feature = "async", feature = "async-std")))]
+    pub async fn async_write_json<T: Serialize>(&self, value: &T) -> Result<()> {
+        self.async_write_frame(&serde_json::to_vec(value)?).await
+    }
+    /// Encode a value with `codec` and write it in one piece no async
+    pub fn write_with<T, C: Codec<T>>(&self, codec: &C, value: &T) -> Result<()> {
+        self.write(&codec.encode(value)?)
+    }
+    /// Encode a value with `codec` and write it in one piece
+    #[cfg(any(feature = "async", feature = "async-std"))]
+    pub async fn async_write_with<T, C: Codec<T>>(&self, codec: &C, value: &T) -> Result<()> {
+        self.async_write(&codec.encode(value)?).await
+    }
+    /// Check if a reader has the pipe open, so a write wouldn't block waiting for one
+    /// Not side-effect free: when a reader is there, the check opens and closes a write end. A
+    /// reader with no other writer attached sees that close as EOF, so a pending `async_read` or
+    /// `read` resolves with an empty session. Use it before the real writer connects only when
+    /// readers can tell an empty session apart, e.g. with `Reader::async_read_opt`
+    pub fn reader_connected(&self) -> Result<bool> {
+        use std::os::unix::fs::OpenOptionsExt;
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .custom_flags(nix::fcntl::OFlag::O_NONBLOCK.bits())
+            .open(&self.path.inner);
+        match file.map_err(|err| self.no_reader(err)) {
+            Ok(_) => Ok(true),
+            Err(PipeError::NoReader(_)) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+    /// Open the pipe once and keep the descriptor for many writes
+    /// Resolves once a reader has the pipe open
+    #[cfg(feature = "async")]
+    pub async fn connect(&self) -> Result<ConnectedWriter> {
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create(false)
+            .open(&self.path.inner)
+            .await
+            .map_err(self.path.io_context("open"))?;
+        Ok(ConnectedWriter::new(self.path.configure_handle(file)?))
+    }
+    /// Open the pipe once and keep the descriptor for many writes no async
+    /// Blocks until a reader has the pipe open
+    pub fn connect_blocking(&self) -> Result<SyncConnectedWriter> {
+        use std::os::unix::io::AsRawFd;
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(false)
+            .open(&self.path.inner)
+            .map_err(self.path.io_context("open"))?;
+        if self.path.nonblocking {
+            set_nonblocking(file.as_raw_fd(), true)?;
+        }
+        Ok(SyncConnectedWriter {
+            file: self.path.configure_handle(file)?,
+        })
+    }
+    /// Open the pipe once for many writes, waiting at most `timeout` for a reader
+    /// Fails with `PipeError::Timeout` if none shows up in time. The open is retried every 10 ms
+    /// rather than blocking, so giving up never leaves a thread stuck in `open`
+    #[cfg(feature = "async")]
+    pub async fn connect_timeout(&self, timeout: Duration) -> Result<ConnectedWriter> {
+        use std::os::unix::io::AsRawFd;
+        let deadline = time::Instant::now() + timeout;
+        loop {
+            match self.open(true) {
+                Ok(file) => {
+                    // ConnectedWriter relies on tokio's blocking pool, not on readiness
+                    set_nonblocking(file.as_raw_fd(), false)?;
+                    return Ok(ConnectedWriter::new(fs::File::from_std(file)));
+                }
+                Err(PipeError::NoReader(_)) if time::Instant::now() < deadline => {
+                    time::sleep_until(deadline.min(time::Instant::now() + CONNECT_POLL_INTERVAL))
+                        .await;
+                }
+                Err(PipeError::NoReader(_)) => return Err(PipeError::Timeout(Vec::new())),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+    /// Open the write side and hand out the raw file, with `O_NONBLOCK` set if `nonblocking` is true
+    /// A blocking open waits until a reader opens the pipe. A non-blocking one fails with
+    /// `PipeError::NoReader` (`ENXIO`) if there is none, and later writes to a full pipe fail
+    /// with `WouldBlock`
+    pub fn open(&self, nonblocking: bool) -> Result<std::fs::File> {
+        use std::os::unix::fs::OpenOptionsExt;
+        let flags = if nonblocking {
+            nix::fcntl::OFlag::O_NONBLOCK
+        } else {
+            nix::fcntl::OFlag::empty()
+        };
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .custom_flags(flags.bits())
+            .open(&self.path.inner)
+            .map_err(|err| self.no_reader(err))?;
+        Ok(self.path.configure_handle(file)?)
+    }
+    /// Open a stream that keeps the pipe open and implements `AsyncWrite`
+    /// Fails with `PipeError::NoReader` unless a reader already has the pipe open,
+    /// must be called from within a tokio runtime
+    #[cfg(feature = "async")]
+    pub fn open_stream(&self) -> Result<PipeWriteStream> {
+        let stream = PipeWriteStream::open(&self.path.inner).map_err(|err| self.no_reader(err))?;
+        Ok(self.path.configure_handle(stream)?)
+    }
+    /// Translate the `ENXIO` of a non-blocking open into `PipeError::NoReader`
+    fn no_reader(&self, err: std::io::Error) -> PipeError {
+        if err.raw_os_error() == Some(Errno::ENXIO as i32) {
+            PipeError::NoReader(self.path.inner.to_path_buf())
+        } else {
+            self.path.io_context("open")(err)
+        }
+    }
+}
+
+impl fmt::Debug for Writer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Writer")
+            .field("path", &self.path.inner)
+            .field("exists", &self.path.exists())
+            .finish()
+    }
+}
+
+/// A writer holding an open descriptor on the named pipe, closed on drop
+#[cfg(feature = "async")]
+pub struct ConnectedWriter {
+    file: fs::File,
+    #[cfg(feature = "metrics")]
+    metrics: Counters,
+}
+
+#[cfg(feature = "async")]
+impl ConnectedWriter {
+    fn new(file: fs::File) -> Self {
+        Self {
+            file,
+            #[cfg(feature = "metrics")]
+            metrics: Counters::default(),
+        }
+    }
     /// Write byte data to the pipe
-    pub async fn async_write(&self, data: &[u8]) -> io::Result<()> {
-        self._write(data).await
+    #[cfg(feature = "async")]
+    pub async fn write(&mut self, data: &[u8]) -> Result<()> {
+        use io::AsyncWriteExt;
+        let result = async {
+            self.file.write_all(data).await?;
+            // tokio's File completes writes in the background, wait so errors surface here
+            self.file.flush().await
+        }
+        .await;
+        #[cfg(feature = "metrics")]
+        match result {
+            Ok(()) => self.metrics.written(data.len()),
+            Err(_) => self.metrics.write_error(),
+        }
+        Ok(result?)
     }
     /// Write &str data to the pipe
-    pub fn write_str(&self, data: String) -> std::io::Result<()> {
-        let mut buffer = std::fs::File::create(&self.path.inner.to_str().unwrap())?;
-        buffer.write_all(data.as_bytes())?;
+    #[cfg(feature = "async")]
+    pub async fn write_str(&mut self, data: &str) -> Result<()> {
+        self.write(data.as_bytes()).await
+    }
+    /// Write byte data, giving up with `PipeError::Timeout` if it can't all go out in time
+    /// The error holds the bytes that did reach the pipe, so the caller knows where to resume
+    #[cfg(feature = "async")]
+    pub async fn write_timeout(&mut self, data: &[u8], timeout: Duration) -> Result<()> {
+        use io::{unix::AsyncFd, Interest};
+        use std::os::unix::io::AsRawFd;
+        self.flush().await?;
+        // Polling for readiness needs a non-blocking descriptor, put it back once done
+        let fd = self.file.as_raw_fd();
+        let nonblocking = NonblockingGuard::new(fd)?;
+        let mut written = 0;
+        let result = async {
+            let async_fd = AsyncFd::with_interest(fd, Interest::WRITABLE)?;
+            while written < data.len() {
+                let mut guard = async_fd.writable().await?;
+                if let Ok(count) = guard.try_io(|fd| {
+                    unistd::write(*fd.get_ref(), &data[written..]).map_err(std::io::Error::from)
+                }) {
+                    written += count?;
+                }
+            }
+            Ok::<_, std::io::Error>(())
+        };
+        let result = time::timeout(timeout, result).await;
+        #[cfg(feature = "metrics")]
+        {
+            self.metrics.written(written);
+            if !matches!(result, Ok(Ok(()))) {
+                self.metrics.write_error();
+            }
+        }
+        nonblocking.restore()?;
+        match result {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(PipeError::Timeout(data[..written].to_vec())),
+        }
+    }
+    /// Wait until the pipe has room for more data, e.g. after a slow reader let it fill up
+    /// Room for one byte is enough to resolve, a following large write may still block. Takes
+    /// `&mut self` because the descriptor is registered with the reactor for the duration of the
+    /// call and a descriptor can only be registered once, so there is one wait at a time
+    #[cfg(feature = "async")]
+    pub async fn writable(&mut self) -> Result<()> {
+        use io::{unix::AsyncFd, Interest};
+        use std::os::unix::io::AsRawFd;
+        let async_fd = AsyncFd::with_interest(self.file.as_raw_fd(), Interest::WRITABLE)?;
+        let _guard = async_fd.writable().await?;
         Ok(())
     }
-    /// Write &str data to the pipe
-    pub async fn async_write_str(&self, data: &str) -> io::Result<()> {
-        self._write(data.as_bytes()).await
+    /// Write several buffers in order as one piece, returns the total number of bytes written
+    /// The slices go straight to `writev` without being copied into one buffer. Up to `PIPE_BUF`
+    /// bytes stay atomic, a larger batch goes out over several calls as the reader makes room
+    #[cfg(feature = "async")]
+    pub async fn write_vectored(&mut self, bufs: &[std::io::IoSlice<'_>]) -> Result<usize> {
+        use io::{unix::AsyncFd, Interest};
+        use nix::sys::uio;
+        use std::io::IoSlice;
+        use std::os::unix::io::AsRawFd;
+        self.flush().await?;
+        // Same as `write_timeout`, readiness needs a non-blocking descriptor for the duration
+        let fd = self.file.as_raw_fd();
+        let nonblocking = NonblockingGuard::new(fd)?;
+        let total = bufs.iter().map(|buf| buf.len()).sum();
+        let mut written = 0;
+        let result = async {
+            let async_fd = AsyncFd::with_interest(fd, Interest::WRITABLE)?;
+            let mut slices = bufs.to_vec();
+            let mut remaining = &mut slices[..];
+            while written < total {
+                let mut guard = async_fd.writable().await?;
+                if let Ok(count) = guard.try_io(|fd| {
+                    uio::writev(*fd.get_ref(), remaining).map_err(std::io::Error::from)
+                }) {
+                    let count = count?;
+                    written += count;
+                    IoSlice::advance_slices(&mut remaining, count);
+                }
+            }
+            Ok::<_, std::io::Error>(())
+        }
+        .await;
+        #[cfg(feature = "metrics")]
+        {
+            self.metrics.written(written);
+            if result.is_err() {
+                self.metrics.write_error();
+            }
+        }
+        nonblocking.restore()?;
+        result?;
+        Ok(total)
+    }
+    /// Write one line of text to the pipe, appending `\n` unless `line` already ends with one
+    /// Many calls over the same descriptor form a stream that `Reader::lines` reads back
+    #[cfg(feature = "async")]
+    pub async fn write_line(&mut self, line: &str) -> Result<()> {
+        self.write(terminate_line(line).as_bytes()).await
+    }
+    /// Write byte data, then wait until the reader has taken everything out of the pipe
+    /// This only confirms the kernel buffer is empty again. The reader may still hold the data
+    /// in its own buffers and not have acted on it, so it is no substitute for an acknowledgement
+    #[cfg(feature = "async")]
+    pub async fn write_and_wait_drained(&mut self, data: &[u8]) -> Result<()> {
+        use std::os::unix::io::AsRawFd;
+        self.write(data).await?;
+        while bytes_readable(self.file.as_raw_fd())? > 0 {
+            time::sleep(DRAIN_POLL_INTERVAL).await;
+        }
+        Ok(())
+    }
+    /// Counters of the bytes written and write errors so far
+    /// A timed out `write_timeout` counts as an error, the bytes it got out still count as written
+    #[cfg(feature = "metrics")]
+    pub fn metrics_snapshot(&self) -> PipeMetrics {
+        self.metrics.snapshot()
+    }
+    /// Wait until everything written so far has been handed to the kernel pipe buffer
+    /// FIFOs have no storage to `fsync`, and this doesn't wait for the reader to consume the data.
+    /// `write` already flushes, this is the sync point after writing through other handles
+    #[cfg(feature = "async")]
+    pub async fn flush(&mut self) -> Result<()> {
+        use io::AsyncWriteExt;
+        Ok(self.file.flush().await?)
+    }
+}
+
+/// The descriptor is owned by the writer, callers must not close it
+#[cfg(feature = "async")]
+impl std::os::unix::io::AsRawFd for ConnectedWriter {
+    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+/// Default buffer size of a `BufferedWriter`, same as `std::io::BufWriter`
+#[cfg(feature = "async")]
+const DEFAULT_BUF_SIZE: usize = 8 * 1024;
+
+/// Collects small writes in memory and hands them to a `ConnectedWriter` in larger batches
+/// The buffer is written out once the next write wouldn't fit, on `flush`, and on drop
+#[cfg(feature = "async")]
+pub struct BufferedWriter {
+    inner: Option<ConnectedWriter>,
+    buf: Vec<u8>,
+}
+
+#[cfg(feature = "async")]
+impl BufferedWriter {
+    /// Wrap `inner` with an 8 KiB buffer
+    pub fn new(inner: ConnectedWriter) -> Self {
+        Self::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+    /// Wrap `inner` with a buffer holding up to `capacity` bytes
+    pub fn with_capacity(capacity: usize, inner: ConnectedWriter) -> Self {
+        Self {
+            inner: Some(inner),
+            buf: Vec::with_capacity(capacity),
+        }
+    }
+    /// Buffer byte data, writing out the buffer first if `data` doesn't fit
+    /// Data at least as large as the buffer is written straight through
+    pub async fn write(&mut self, data: &[u8]) -> Result<()> {
+        if self.buf.len() + data.len() > self.buf.capacity() {
+            self.flush().await?;
+        }
+        if data.len() >= self.buf.capacity() {
+            self.connected().write(data).await
+        } else {
+            self.buf.extend_from_slice(data);
+            Ok(())
+        }
+    }
+    /// Buffer &str data
+    pub async fn write_str(&mut self, data: &str) -> Result<()> {
+        self.write(data.as_bytes()).await
+    }
+    /// Write out everything buffered so far in one write
+    pub async fn flush(&mut self) -> Result<()> {
+        if !self.buf.is_empty() {
+            let connected = self.inner.as_mut().expect("writer is only taken on drop");
+            let result = connected.write(&self.buf).await;
+            self.buf.clear();
+            result?;
+        }
+        Ok(())
+    }
+    fn connected(&mut self) -> &mut ConnectedWriter {
+        self.inner.as_mut().expect("writer is only taken on drop")
+    }
+}
+
+/// Writes out what is still buffered with a blocking write, errors are ignored
+/// Call `flush` before dropping to see them. Unflushed data is a likely bug, so it is reported
+/// through `tracing` with the `tracing` feature, or on stderr in debug builds
+#[cfg(feature = "async")]
+impl Drop for BufferedWriter {
+    fn drop(&mut self) {
+        if let Some(connected) = self.inner.take() {
+            if !self.buf.is_empty() {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(
+                    bytes = self.buf.len(),
+                    "BufferedWriter dropped without flush, writing out the buffer with a blocking write"
+                );
+                #[cfg(all(debug_assertions, not(feature = "tracing")))]
+                eprintln!(
+                    "BufferedWriter dropped without flush, writing out {} buffered bytes with a blocking write",
+                    self.buf.len()
+                );
+                // Every ConnectedWriter write waits for its flush, so no operation is in flight
+                if let Ok(mut file) = connected.file.try_into_std() {
+                    let _ = file.write_all(&self.buf);
+                }
+            }
+        }
+    }
+}
+
+/// A blocking writer holding an open descriptor on the named pipe, closed on drop
+/// Writes go straight to the kernel, so `flush` has nothing to do
+pub struct SyncConnectedWriter {
+    file: std::fs::File,
+}
+
+impl SyncConnectedWriter {
+    /// Switch the descriptor in or out of non-blocking mode without reopening the pipe
+    /// Once non-blocking, a write into a full pipe fails with `WouldBlock` or writes only part of
+    /// the data instead of waiting for the reader to make room
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        use std::os::unix::io::AsRawFd;
+        Ok(set_nonblocking(self.as_raw_fd(), nonblocking)?)
+    }
+}
+
+impl Write for SyncConnectedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.file.write(buf)
+    }
+    fn write_vectored(&mut self, bufs: &[std::io::IoSlice<'_>]) -> std::io::Result<usize> {
+        self.file.write_vectored(bufs)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// The descriptor is owned by the writer, callers must not close it
+impl std::os::unix::io::AsRawFd for SyncConnectedWriter {
+    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        self.file.as_raw_fd()
     }
 }
 
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "async")]
     use tokio::runtime::Handle;
+    #[cfg(feature = "async")]
     use tokio::{io, task};
 
+    /// Held by every test that checks file modes, so none of them overlaps with a test that
+    /// changes the process-wide default mode
+    static MODE_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    #[cfg(feature = "async")]
     #[ignore]
     #[tokio::test]
     async fn write_and_read_threaded() -> io::Result<()> {
@@ -164,10 +2275,11 @@ mod tests {
         let _a = t_write.join().unwrap();
         let read_result = t_read.join().unwrap()?;
         assert_eq!(read_result, data_to_send);
-        handle_del.block_on(pipe.delete())
+        Ok(handle_del.block_on(pipe.delete())?)
         // _a
     }
 
+    #[cfg(feature = "async")]
     #[tokio::test]
     async fn ensure_on_write() -> io::Result<()> {
         task::spawn(async move {
@@ -187,11 +2299,12 @@ mod tests {
             let _a = t1.await?;
             let read_result = t2.await?;
             assert_eq!(read_result.unwrap(), data_to_send.as_bytes());
-            pipe.delete().await
+            Ok(pipe.delete().await?)
         })
         .await?
     }
 
+    #[cfg(feature = "async")]
     #[tokio::test]
     async fn ensure_on_read() -> io::Result<()> {
         task::spawn(async move {
@@ -205,11 +2318,12 @@ mod tests {
             let _a = t1.await?;
             let read_result = t2.await?;
             assert_eq!(read_result.unwrap(), data_to_send.as_bytes());
-            pipe.delete().await
+            Ok(pipe.delete().await?)
         })
         .await?
     }
 
+    #[cfg(feature = "async")]
     #[tokio::test]
     async fn write_and_read_async() -> io::Result<()> {
         task::spawn(async move {
@@ -223,8 +2337,2393 @@ mod tests {
             let _a = t1.await?;
             let read_result = t2.await?;
             assert_eq!(read_result.unwrap(), data_to_send);
-            pipe.delete().await
+            Ok(pipe.delete().await?)
         })
         .await?
     }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn create_with_mode() -> io::Result<()> {
+        let _mode_lock = MODE_LOCK.lock().await;
+        use nix::sys::stat::{stat, Mode};
+        let pipe = super::Pipe::builder("/tmp/test_pipe_4")
+            .mode(Mode::from_bits_truncate(0o600))
+            .build();
+        pipe.ensure_exists().unwrap();
+        let st = stat("/tmp/test_pipe_4").unwrap();
+        assert_eq!(st.st_mode & 0o777, 0o600);
+        Ok(pipe.delete().await?)
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn read_stream_multiple_messages() -> io::Result<()> {
+        use std::io::Write;
+        use std::{thread, time::Duration};
+        use tokio::io::AsyncBufReadExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_5");
+        pipe.ensure_exists().unwrap();
+        let mut lines = io::BufReader::new(pipe.reader().open_stream().unwrap()).lines();
+        let t_write = thread::spawn(move || -> std::io::Result<()> {
+            let mut file = std::fs::OpenOptions::new()
+                .write(true)
+                .open("/tmp/test_pipe_5")?;
+            for msg in ["first\n", "second\n", "third\n"] {
+                file.write_all(msg.as_bytes())?;
+                thread::sleep(Duration::from_millis(20));
+            }
+            Ok(())
+        });
+        let mut received = Vec::new();
+        while let Some(line) = lines.next_line().await? {
+            received.push(line);
+        }
+        t_write.join().unwrap()?;
+        assert_eq!(received, ["first", "second", "third"]);
+        Ok(pipe.delete().await?)
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn write_stream_copy() -> io::Result<()> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let pipe = super::Pipe::new("/tmp/test_pipe_6");
+        pipe.ensure_exists().unwrap();
+        let mut read_stream = pipe.reader().open_stream().unwrap();
+        let mut write_stream = pipe.writer().open_stream().unwrap();
+        let t_write = task::spawn(async move {
+            let mut source: &[u8] = b"Hello pipe";
+            io::copy(&mut source, &mut write_stream).await?;
+            write_stream.write_all(b" again").await?;
+            write_stream.shutdown().await
+        });
+        let mut received = String::new();
+        read_stream.read_to_string(&mut received).await?;
+        t_write.await??;
+        assert_eq!(received, "Hello pipe again");
+        Ok(pipe.delete().await?)
+    }
+
+    #[test]
+    fn sync_writes_append() {
+        use std::thread;
+        let pipe = super::Pipe::new("/tmp/test_pipe_7");
+        pipe.ensure_exists().unwrap();
+        let writer = pipe.writer();
+        let reader = pipe.reader();
+        let t_read = thread::spawn(move || reader.read());
+        // Hold a write end open so the reader doesn't see EOF between the two writes
+        let keep_open = std::fs::OpenOptions::new()
+            .write(true)
+            .open("/tmp/test_pipe_7")
+            .unwrap();
+        writer.write(b"Hello ").unwrap();
+        writer.write_str("pipe".to_string()).unwrap();
+        drop(keep_open);
+        let read_result = t_read.join().unwrap().unwrap();
+        assert_eq!(read_result, b"Hello pipe");
+        std::fs::remove_file("/tmp/test_pipe_7").unwrap();
+    }
+
+    #[test]
+    fn is_fifo_missing_path() {
+        let pipe = super::Pipe::new("/tmp/test_pipe_8");
+        assert!(!pipe.is_fifo().unwrap());
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn is_fifo_real_fifo() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_9");
+        pipe.ensure_exists().unwrap();
+        assert!(pipe.is_fifo().unwrap());
+        Ok(pipe.delete().await?)
+    }
+
+    #[cfg(not(any(feature = "async", feature = "async-std")))]
+    #[test]
+    fn sync_delete_only_removes_fifos() {
+        use super::PipeError;
+        std::fs::write("/tmp/test_pipe_113", b"not a pipe").unwrap();
+        let file = super::Pipe::new("/tmp/test_pipe_113");
+        assert!(matches!(file.delete(), Err(PipeError::NotAFifo(_))));
+        assert!(std::path::Path::new("/tmp/test_pipe_113").exists());
+        std::fs::remove_file("/tmp/test_pipe_113").unwrap();
+        let pipe = super::Pipe::new("/tmp/test_pipe_114");
+        pipe.ensure_exists().unwrap();
+        pipe.clone().delete().unwrap();
+        assert!(!pipe.exists());
+        pipe.delete().unwrap();
+    }
+
+    #[test]
+    fn ensure_exists_regular_file() {
+        use super::PipeError;
+        std::fs::write("/tmp/test_pipe_10", b"not a pipe").unwrap();
+        let pipe = super::Pipe::new("/tmp/test_pipe_10");
+        assert!(!pipe.is_fifo().unwrap());
+        assert!(matches!(pipe.ensure_exists(), Err(PipeError::NotAFifo(_))));
+        std::fs::remove_file("/tmp/test_pipe_10").unwrap();
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn read_timeout_without_writer() -> io::Result<()> {
+        use std::time::Duration;
+        let pipe = super::Pipe::new("/tmp/test_pipe_11");
+        pipe.ensure_exists().unwrap();
+        let read_result = pipe
+            .reader()
+            .read_timeout(Duration::from_millis(50))
+            .await
+            .unwrap();
+        assert_eq!(read_result, None);
+        Ok(pipe.delete().await?)
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn read_timeout_with_writer() -> io::Result<()> {
+        use std::time::Duration;
+        let pipe = super::Pipe::new("/tmp/test_pipe_12");
+        pipe.ensure_exists().unwrap();
+        let writer = pipe.writer();
+        let reader = pipe.reader();
+        let data_to_send = "Hello pipe";
+        let t2 = task::spawn(async move { reader.read_timeout(Duration::from_secs(5)).await });
+        let t1 = task::spawn(async move { writer.async_write(data_to_send.as_bytes()).await });
+        t1.await?.unwrap();
+        let read_result = t2.await?.unwrap();
+        assert_eq!(read_result.as_deref(), Some(data_to_send.as_bytes()));
+        Ok(pipe.delete().await?)
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn frame_larger_than_pipe_buffer() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_13");
+        pipe.ensure_exists().unwrap();
+        let writer = pipe.writer();
+        let reader = pipe.reader();
+        let data_to_send: Vec<u8> = (0..256 * 1024).map(|i| i as u8).collect();
+        let expected = data_to_send.clone();
+        let t1 = task::spawn(async move { writer.async_write_frame(&data_to_send).await });
+        let t2 = task::spawn(async move { reader.async_read_frame().await });
+        t1.await?.unwrap();
+        let read_result = t2.await?.unwrap();
+        assert_eq!(read_result, expected);
+        Ok(pipe.delete().await?)
+    }
+
+    #[test]
+    fn frame_sync() {
+        use std::thread;
+        let pipe = super::Pipe::new("/tmp/test_pipe_14");
+        pipe.ensure_exists().unwrap();
+        let writer = pipe.writer();
+        let reader = pipe.reader();
+        let t_write = thread::spawn(move || writer.write_frame(b"Hello pipe"));
+        let read_result = reader.read_frame().unwrap();
+        t_write.join().unwrap().unwrap();
+        assert_eq!(read_result, b"Hello pipe");
+        std::fs::remove_file("/tmp/test_pipe_14").unwrap();
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn connected_writer_many_writes() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_15");
+        pipe.ensure_exists().unwrap();
+        let writer = pipe.writer();
+        let reader = pipe.reader();
+        let t1 = task::spawn(async move {
+            let mut connected = writer.connect().await?;
+            for i in 0..100 {
+                connected.write_str(&format!("{};", i)).await?;
+            }
+            super::Result::Ok(())
+        });
+        let t2 = task::spawn(async move { reader.async_read_str().await });
+        t1.await?.unwrap();
+        let read_result = t2.await?.unwrap();
+        let expected: String = (0..100).map(|i| format!("{};", i)).collect();
+        assert_eq!(read_result, expected);
+        Ok(pipe.delete().await?)
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn lines_stream() -> io::Result<()> {
+        use futures::TryStreamExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_16");
+        pipe.ensure_exists().unwrap();
+        let writer = pipe.writer();
+        let lines = pipe.reader().lines().unwrap();
+        let t1 = task::spawn(async move { writer.async_write_str("a\nb\nc\n").await });
+        let read_result: Vec<String> = lines.try_collect().await?;
+        t1.await?.unwrap();
+        assert_eq!(read_result, ["a", "b", "c"]);
+        Ok(pipe.delete().await?)
+    }
+
+    #[test]
+    fn guard_deletes_on_drop() {
+        let pipe = super::Pipe::new("/tmp/test_pipe_17");
+        let guard = pipe.create_temp_guard().unwrap();
+        assert!(guard.is_fifo().unwrap());
+        drop(guard);
+        assert!(!pipe.exists());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn set_capacity() {
+        let pipe = super::Pipe::new("/tmp/test_pipe_18");
+        let guard = pipe.create_temp_guard().unwrap();
+        let granted = guard.set_capacity(128 * 1024).unwrap();
+        assert!(granted >= 128 * 1024);
+        assert!(guard.capacity().unwrap() > 0);
+    }
+
+    #[test]
+    fn read_exact_chunked() {
+        use std::io::Write;
+        use std::{thread, time::Duration};
+        let pipe = super::Pipe::new("/tmp/test_pipe_19");
+        let guard = pipe.create_temp_guard().unwrap();
+        let reader = guard.reader();
+        let t_read = thread::spawn(move || {
+            let mut record = [0u8; 10];
+            reader.read_exact(&mut record).map(|_| record)
+        });
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .open("/tmp/test_pipe_19")
+            .unwrap();
+        file.write_all(b"01234").unwrap();
+        thread::sleep(Duration::from_millis(20));
+        file.write_all(b"56789").unwrap();
+        let record = t_read.join().unwrap().unwrap();
+        assert_eq!(&record, b"0123456789");
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn async_read_exact_early_eof() -> io::Result<()> {
+        use super::PipeError;
+        let pipe = super::Pipe::new("/tmp/test_pipe_20");
+        pipe.ensure_exists().unwrap();
+        let writer = pipe.writer();
+        let reader = pipe.reader();
+        let t1 = task::spawn(async move { writer.async_write(b"short").await });
+        let t2 = task::spawn(async move {
+            let mut record = [0u8; 10];
+            reader.async_read_exact(&mut record).await
+        });
+        t1.await?.unwrap();
+        match t2.await? {
+            Err(PipeError::PathIo { source: err, .. }) => {
+                assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof)
+            }
+            other => panic!("expected UnexpectedEof, got {:?}", other),
+        }
+        Ok(pipe.delete().await?)
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn write_partial_reports_count() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_21");
+        pipe.ensure_exists().unwrap();
+        let writer = pipe.writer();
+        let reader = pipe.reader();
+        let data_to_send = "Hello pipe";
+        let t1 =
+            task::spawn(async move { writer.async_write_partial(data_to_send.as_bytes()).await });
+        let t2 = task::spawn(async move { reader.async_read_str().await });
+        let written = t1.await?.unwrap();
+        let read_result = t2.await?.unwrap();
+        assert_eq!(written, data_to_send.len());
+        assert_eq!(read_result, data_to_send);
+        Ok(pipe.delete().await?)
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn write_partial_short_count() -> io::Result<()> {
+        use io::AsyncReadExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_108");
+        let guard = pipe.create_temp_guard().unwrap();
+        // Nothing is read until the write returns, so only the pipe's capacity fits
+        let mut stream = guard.reader().open_stream().unwrap();
+        let data = vec![7u8; 4 * 1024 * 1024];
+        let written = guard.writer().async_write_partial(&data).await.unwrap();
+        assert!(written > 0 && written < data.len(), "wrote {}", written);
+        let mut received = Vec::new();
+        stream.read_to_end(&mut received).await?;
+        assert_eq!(received.len(), written);
+        Ok(())
+    }
+
+    #[cfg(all(target_os = "linux", feature = "async"))]
+    #[tokio::test]
+    async fn persistent_reader_many_writers() -> io::Result<()> {
+        use futures::StreamExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_22");
+        let guard = pipe.create_temp_guard().unwrap();
+        let mut reader = guard.reader().open_persistent().unwrap();
+        guard.writer().async_write_frame(b"first").await.unwrap();
+        guard.writer().async_write_frame(b"second").await.unwrap();
+        assert_eq!(reader.read_frame().await.unwrap(), b"first");
+        assert_eq!(reader.read_frame().await.unwrap(), b"second");
+        let mut lines = reader.lines();
+        guard.writer().async_write_str("a\n").await.unwrap();
+        guard.writer().async_write_str("b\n").await.unwrap();
+        assert_eq!(lines.next().await.unwrap()?, "a");
+        assert_eq!(lines.next().await.unwrap()?, "b");
+        Ok(())
+    }
+
+    #[cfg(all(feature = "serde", feature = "async"))]
+    #[tokio::test]
+    async fn json_round_trip() -> io::Result<()> {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Inner {
+            id: u32,
+            tags: Vec<String>,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Message {
+            name: String,
+            inner: Inner,
+            parent: Option<Box<Inner>>,
+        }
+
+        let pipe = super::Pipe::new("/tmp/test_pipe_23");
+        pipe.ensure_exists().unwrap();
+        let writer = pipe.writer();
+        let reader = pipe.reader();
+        let message = Message {
+            name: "Hello pipe".to_string(),
+            inner: Inner {
+                id: 7,
+                tags: vec!["a".to_string(), "b".to_string()],
+            },
+            parent: Some(Box::new(Inner {
+                id: 1,
+                tags: Vec::new(),
+            })),
+        };
+        let t2 = task::spawn(async move { reader.async_read_json::<Message>().await });
+        writer.async_write_json(&message).await.unwrap();
+        let read_result = t2.await?.unwrap();
+        assert_eq!(read_result, message);
+        Ok(pipe.delete().await?)
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn write_atomic_limits() -> io::Result<()> {
+        use super::PipeError;
+        let pipe = super::Pipe::new("/tmp/test_pipe_24");
+        let guard = pipe.create_temp_guard().unwrap();
+        let limit = guard.pipe_buf_size().unwrap();
+        assert!(limit >= 512);
+        let writer = guard.writer();
+        match writer.async_write_atomic(&vec![0u8; limit + 1]).await {
+            Err(PipeError::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::InvalidInput),
+            other => panic!("expected InvalidInput, got {:?}", other),
+        }
+        let reader = guard.reader();
+        let t2 = task::spawn(async move { reader.async_read().await });
+        writer.async_write_atomic(&vec![1u8; limit]).await.unwrap();
+        assert_eq!(t2.await?.unwrap(), vec![1u8; limit]);
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn message_stream_per_writer() -> io::Result<()> {
+        use futures::StreamExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_25");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let mut messages = Box::pin(guard.reader().into_message_stream());
+        let t1 = task::spawn(async move { writer.async_write_str("first").await });
+        assert_eq!(messages.next().await.unwrap()?, b"first");
+        t1.await?.unwrap();
+        let writer = guard.writer();
+        let t2 = task::spawn(async move { writer.async_write_str("second").await });
+        assert_eq!(messages.next().await.unwrap()?, b"second");
+        t2.await?.unwrap();
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn reader_connected() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_26");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        assert!(!writer.reader_connected().unwrap());
+        let read_stream = guard.reader().open_stream().unwrap();
+        assert!(writer.reader_connected().unwrap());
+        drop(read_stream);
+        assert!(!writer.reader_connected().unwrap());
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn reader_connected_ends_waiting_read() -> io::Result<()> {
+        use std::time::Duration;
+        let pipe = super::Pipe::new("/tmp/test_pipe_110");
+        let guard = pipe.create_temp_guard().unwrap();
+        let reader = guard.reader();
+        let t1 = task::spawn(async move { reader.async_read().await });
+        let writer = guard.writer();
+        while !writer.reader_connected().unwrap() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        // The probe was the only writer, its close ends the session before any data was sent
+        assert_eq!(t1.await?.unwrap(), b"");
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn lazy_create_keeps_mode() -> io::Result<()> {
+        let _mode_lock = MODE_LOCK.lock().await;
+        use nix::sys::stat::{stat, Mode};
+        let pipe = super::Pipe::with_mode("/tmp/test_pipe_28", Mode::from_bits_truncate(0o640));
+        let writer = pipe.writer();
+        writer.pipe_exists().unwrap();
+        let st = stat("/tmp/test_pipe_28").unwrap();
+        assert_eq!(st.st_mode & 0o777, 0o640);
+        Ok(pipe.delete().await?)
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn try_delete_twice() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_29");
+        pipe.ensure_exists().unwrap();
+        assert!(pipe.try_delete().await.unwrap());
+        assert!(!pipe.try_delete().await.unwrap());
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn demux_routes_channels() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_98");
+        let guard = pipe.create_temp_guard().unwrap();
+        let mut receivers = guard.reader().demux(&[1, 2]).unwrap();
+        let writer = guard.writer();
+        // Every tagged write opens the pipe anew, hold it open so the reader sees one session
+        let session = writer.connect().await.unwrap();
+        let t1 = task::spawn(async move {
+            for (channel, msg) in [(1, "a1"), (2, "b1"), (3, "c1"), (1, "a2"), (2, "b2")] {
+                writer.async_write_tagged(channel, msg.as_bytes()).await?;
+            }
+            super::Result::Ok(())
+        });
+        let mut first = receivers.remove(&1).unwrap();
+        let mut second = receivers.remove(&2).unwrap();
+        assert!(receivers.is_empty());
+        t1.await?.unwrap();
+        drop(session);
+        assert_eq!(first.recv().await.unwrap(), b"a1");
+        assert_eq!(first.recv().await.unwrap(), b"a2");
+        assert_eq!(second.recv().await.unwrap(), b"b1");
+        assert_eq!(second.recv().await.unwrap(), b"b2");
+        assert_eq!(first.recv().await, None);
+        assert_eq!(second.recv().await, None);
+        Ok(())
+    }
+
+    #[test]
+    fn rename_moves_pipe() {
+        use std::path::Path;
+        let mut pipe = super::Pipe::new("/tmp/test_pipe_102");
+        pipe.ensure_exists().unwrap();
+        pipe.rename("/tmp/test_pipe_102_renamed").unwrap();
+        assert_eq!(pipe.path(), Path::new("/tmp/test_pipe_102_renamed"));
+        assert!(!Path::new("/tmp/test_pipe_102").exists());
+        assert!(pipe.is_fifo().unwrap());
+        std::fs::write("/tmp/test_pipe_102", b"keep me").unwrap();
+        match pipe.rename("/tmp/test_pipe_102") {
+            Err(super::PipeError::NotAFifo(path)) => {
+                assert_eq!(path, Path::new("/tmp/test_pipe_102"))
+            }
+            other => panic!("expected NotAFifo, got {:?}", other),
+        }
+        assert_eq!(std::fs::read("/tmp/test_pipe_102").unwrap(), b"keep me");
+        assert!(pipe.is_fifo().unwrap());
+        std::fs::remove_file("/tmp/test_pipe_102").unwrap();
+        pipe.delete_if_exists().unwrap();
+        assert!(matches!(
+            pipe.rename("/tmp/test_pipe_102"),
+            Err(super::PipeError::NotAFifo(_))
+        ));
+    }
+
+    #[test]
+    fn delete_if_exists_keeps_pipe_usable() {
+        let pipe = super::Pipe::new("/tmp/test_pipe_97");
+        pipe.ensure_exists().unwrap();
+        pipe.delete_if_exists().unwrap();
+        assert!(!pipe.exists());
+        pipe.delete_if_exists().unwrap();
+        pipe.recreate(None).unwrap();
+        assert!(pipe.is_fifo().unwrap());
+        pipe.delete_if_exists().unwrap();
+        assert!(!pipe.exists());
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn write_chunked_slow_reader() -> io::Result<()> {
+        use std::time::Duration;
+        use tokio::io::AsyncReadExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_32");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let mut read_stream = guard.reader().open_stream().unwrap();
+        let data_to_send: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+        let expected = data_to_send.clone();
+        let t1 = task::spawn(async move { writer.write_chunked(&data_to_send, 4096).await });
+        let mut received = Vec::new();
+        let mut chunk = vec![0u8; 64 * 1024];
+        loop {
+            let len = read_stream.read(&mut chunk).await?;
+            if len == 0 {
+                break;
+            }
+            received.extend_from_slice(&chunk[..len]);
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        t1.await?.unwrap();
+        assert_eq!(received, expected);
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn open_stream_without_reader() -> io::Result<()> {
+        use super::PipeError;
+        let pipe = super::Pipe::new("/tmp/test_pipe_33");
+        let guard = pipe.create_temp_guard().unwrap();
+        assert!(matches!(
+            guard.writer().open_stream(),
+            Err(PipeError::NoReader(_))
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn open_with_blocking_once() -> io::Result<()> {
+        use tokio::io::AsyncReadExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_34");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let t1 = task::spawn(async move { writer.async_write(b"Hello pipe").await });
+        let mut read_stream = guard
+            .reader()
+            .open_with(super::OpenMode::BlockingOnce)
+            .await
+            .unwrap();
+        let mut received = Vec::new();
+        read_stream.read_to_end(&mut received).await?;
+        t1.await?.unwrap();
+        assert_eq!(received, b"Hello pipe");
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn open_with_non_blocking() -> io::Result<()> {
+        use tokio::io::AsyncReadExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_35");
+        let guard = pipe.create_temp_guard().unwrap();
+        let mut read_stream = guard
+            .reader()
+            .open_with(super::OpenMode::NonBlocking)
+            .await
+            .unwrap();
+        guard.writer().async_write(b"Hello pipe").await.unwrap();
+        let mut received = Vec::new();
+        read_stream.read_to_end(&mut received).await?;
+        assert_eq!(received, b"Hello pipe");
+        Ok(())
+    }
+
+    #[cfg(all(target_os = "linux", feature = "async"))]
+    #[tokio::test]
+    async fn open_with_persistent() -> io::Result<()> {
+        use std::time::Duration;
+        use tokio::io::AsyncReadExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_36");
+        let guard = pipe.create_temp_guard().unwrap();
+        let mut read_stream = guard
+            .reader()
+            .open_with(super::OpenMode::Persistent)
+            .await
+            .unwrap();
+        guard.writer().async_write(b"first").await.unwrap();
+        guard.writer().async_write(b"second").await.unwrap();
+        let mut received = [0u8; 11];
+        read_stream.read_exact(&mut received).await?;
+        assert_eq!(&received, b"firstsecond");
+        // No EOF once the writers are gone, the read just keeps waiting
+        let mut rest = Vec::new();
+        let read_result = tokio::time::timeout(
+            Duration::from_millis(50),
+            read_stream.read_to_end(&mut rest),
+        )
+        .await;
+        assert!(read_result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_exists_creates_parents() {
+        let _ = std::fs::remove_dir_all("/tmp/test_pipe_dir_37");
+        let pipe = super::Pipe::new("/tmp/test_pipe_dir_37/a/cmd");
+        assert!(pipe.ensure_exists().is_err());
+        pipe.ensure_exists_all().unwrap();
+        assert!(pipe.is_fifo().unwrap());
+        let pipe = super::Pipe::builder("/tmp/test_pipe_dir_37/b/c/cmd")
+            .create_parents(true)
+            .build();
+        pipe.ensure_exists().unwrap();
+        assert!(pipe.is_fifo().unwrap());
+        std::fs::remove_dir_all("/tmp/test_pipe_dir_37").unwrap();
+    }
+
+    #[cfg(all(target_os = "linux", feature = "async"))]
+    #[tokio::test]
+    async fn builder_applies_every_option() -> io::Result<()> {
+        let _mode_lock = MODE_LOCK.lock().await;
+        use nix::fcntl::{fcntl, FcntlArg, FdFlag, OFlag};
+        use nix::sys::stat::{stat, Mode};
+        use std::os::unix::io::AsRawFd;
+        let _ = std::fs::remove_dir_all("/tmp/test_pipe_dir_96");
+        let pipe = super::Pipe::builder("/tmp/test_pipe_dir_96/a/cmd")
+            .mode(Mode::from_bits_truncate(0o600))
+            .create_parents(true)
+            .cloexec(false)
+            .nonblocking(true)
+            .capacity(128 * 1024)
+            .create()
+            .unwrap();
+        let st = stat("/tmp/test_pipe_dir_96/a/cmd").unwrap();
+        assert_eq!(st.st_mode & 0o777, 0o600);
+        let _reader = pipe.reader().open_persistent().unwrap();
+        let writer = pipe.writer().connect_blocking().unwrap();
+        let fd = writer.as_raw_fd();
+        let flags = OFlag::from_bits_truncate(fcntl(fd, FcntlArg::F_GETFL).unwrap());
+        assert!(flags.contains(OFlag::O_NONBLOCK));
+        let fd_flags = FdFlag::from_bits_truncate(fcntl(fd, FcntlArg::F_GETFD).unwrap());
+        assert!(!fd_flags.contains(FdFlag::FD_CLOEXEC));
+        assert!(fcntl(fd, FcntlArg::F_GETPIPE_SZ).unwrap() >= 128 * 1024);
+
+        let pipe = super::Pipe::builder("/tmp/test_pipe_dir_96/a/cmd").build();
+        let _reader = pipe.reader().open_persistent().unwrap();
+        let writer = pipe.writer().connect_blocking().unwrap();
+        let fd = writer.as_raw_fd();
+        let flags = OFlag::from_bits_truncate(fcntl(fd, FcntlArg::F_GETFL).unwrap());
+        assert!(!flags.contains(OFlag::O_NONBLOCK));
+        let fd_flags = FdFlag::from_bits_truncate(fcntl(fd, FcntlArg::F_GETFD).unwrap());
+        assert!(fd_flags.contains(FdFlag::FD_CLOEXEC));
+        std::fs::remove_dir_all("/tmp/test_pipe_dir_96")
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn read_bounded_limits() -> io::Result<()> {
+        use std::time::Duration;
+        let pipe = super::Pipe::new("/tmp/test_pipe_99");
+        let guard = pipe.create_temp_guard().unwrap();
+        let reader = guard.reader();
+
+        let writer = guard.writer();
+        let t1 = task::spawn(async move { writer.async_write(b"Hello pipe").await });
+        let read = reader
+            .async_read_bounded(64, Duration::from_secs(1))
+            .await
+            .unwrap();
+        t1.await?.unwrap();
+        assert_eq!(read.data, b"Hello pipe");
+        assert!(!read.truncated && !read.timed_out);
+
+        let writer = guard.writer();
+        let t1 = task::spawn(async move { writer.async_write(b"Hello pipe").await });
+        let read = reader
+            .async_read_bounded(5, Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert_eq!(read.data, b"Hello");
+        assert!(read.truncated && !read.timed_out);
+        t1.await?.unwrap();
+
+        let writer = guard.writer();
+        let t1 = task::spawn(async move {
+            let mut connected = writer.connect_timeout(Duration::from_secs(1)).await?;
+            connected.write(b"Hello").await?;
+            // Stay connected past the deadline of the read
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            super::Result::Ok(())
+        });
+        let read = reader
+            .async_read_bounded(64, Duration::from_millis(100))
+            .await
+            .unwrap();
+        assert_eq!(read.data, b"Hello");
+        assert!(!read.truncated && read.timed_out);
+        t1.await?.unwrap();
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn read_capped_limits() -> io::Result<()> {
+        use super::PipeError;
+        let pipe = super::Pipe::new("/tmp/test_pipe_38");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let reader = guard.reader();
+        let t1 = task::spawn(async move { writer.async_write(b"Hello pipe").await });
+        assert_eq!(reader.async_read_capped(10).await.unwrap(), b"Hello pipe");
+        t1.await?.unwrap();
+        let writer = guard.writer();
+        let t1 = task::spawn(async move { writer.async_write(b"Hello pipe").await });
+        assert!(matches!(
+            reader.async_read_capped(5).await,
+            Err(PipeError::LimitExceeded(5))
+        ));
+        t1.await?.unwrap();
+        let writer = guard.writer();
+        let t1 = task::spawn(async move { writer.async_write(b"Hello pipe").await });
+        assert_eq!(reader.async_read_truncated(5).await.unwrap(), b"Hello");
+        // The writer may see a broken pipe once the reader stops early
+        let _ = t1.await?;
+        Ok(())
+    }
+
+    #[test]
+    fn debug_and_display_show_path() {
+        let pipe = super::Pipe::new("/tmp/test_pipe_39");
+        assert_eq!(pipe.to_string(), "/tmp/test_pipe_39");
+        assert!(format!("{:?}", pipe).contains("/tmp/test_pipe_39"));
+        let reader = format!("{:?}", pipe.reader());
+        assert!(reader.contains("/tmp/test_pipe_39") && reader.contains("exists: false"));
+        assert!(format!("{:?}", pipe.writer()).contains("/tmp/test_pipe_39"));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn wait_for_writer_connects() -> io::Result<()> {
+        use std::time::Duration;
+        use tokio::io::AsyncReadExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_40");
+        let guard = pipe.create_temp_guard().unwrap();
+        let reader = guard.reader();
+        let t2 = task::spawn(async move { reader.wait_for_writer(None).await });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!t2.is_finished());
+        let mut connected = guard.writer().connect().await.unwrap();
+        let mut read_stream = t2.await?.unwrap();
+        connected.write(b"Hello pipe").await.unwrap();
+        drop(connected);
+        let mut received = Vec::new();
+        read_stream.read_to_end(&mut received).await?;
+        assert_eq!(received, b"Hello pipe");
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn wait_for_writer_timeout() -> io::Result<()> {
+        use super::PipeError;
+        use std::time::Duration;
+        let pipe = super::Pipe::new("/tmp/test_pipe_41");
+        let guard = pipe.create_temp_guard().unwrap();
+        match guard
+            .reader()
+            .wait_for_writer(Some(Duration::from_millis(50)))
+            .await
+        {
+            Err(PipeError::Timeout(partial)) => assert!(partial.is_empty()),
+            other => panic!("expected Timeout, got {:?}", other.map(|_| ())),
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn wait_for_writer_timeout_busy_pool() {
+        use super::PipeError;
+        use std::time::{Duration, Instant};
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .max_blocking_threads(1)
+            .enable_all()
+            .build()
+            .unwrap();
+        let pipe = super::Pipe::new("/tmp/test_pipe_112");
+        let guard = pipe.create_temp_guard().unwrap();
+        // Keep the only blocking thread busy, so the blocking open never gets to run
+        runtime.spawn_blocking(|| std::thread::sleep(Duration::from_secs(2)));
+        let start = Instant::now();
+        let result = runtime.block_on(
+            guard
+                .reader()
+                .wait_for_writer(Some(Duration::from_millis(50))),
+        );
+        assert!(matches!(result, Err(PipeError::Timeout(_))));
+        assert!(start.elapsed() < Duration::from_secs(1));
+        runtime.shutdown_background();
+    }
+
+    #[test]
+    fn registry_joins_base() {
+        let registry = super::PipeRegistry::new("/tmp/test_pipe_dir_100");
+        let events = registry.get("events").unwrap();
+        assert_eq!(events.path(), registry.get("events").unwrap().path());
+        assert_eq!(
+            events.path(),
+            std::path::Path::new("/tmp/test_pipe_dir_100/events")
+        );
+        assert_ne!(events.path(), registry.get("commands").unwrap().path());
+    }
+
+    #[test]
+    fn registry_stays_in_base() {
+        use super::PipeError;
+        let registry = super::PipeRegistry::new("/tmp/test_pipe_dir_100");
+        for name in ["/abs", "../x", "a/b", "..", ".", ""] {
+            assert!(
+                matches!(registry.get(name), Err(PipeError::InvalidPath(_))),
+                "{:?} was accepted",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn conversions() {
+        use super::Pipe;
+        use std::path::{Path, PathBuf};
+        let expected = Path::new("/tmp/test_pipe_42");
+        let from_str: Pipe = "/tmp/test_pipe_42".into();
+        let from_string: Pipe = String::from("/tmp/test_pipe_42").into();
+        let from_path: Pipe = expected.into();
+        let from_path_buf: Pipe = PathBuf::from("/tmp/test_pipe_42").into();
+        for pipe in [from_str, from_string, from_path, from_path_buf] {
+            assert_eq!(pipe.as_ref(), expected);
+        }
+        let pipe = Pipe::new("/tmp/test_pipe_42");
+        assert_eq!(pipe.path(), expected);
+        assert_eq!(PathBuf::from(pipe), expected);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn drain_pending_bytes() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_43");
+        let guard = pipe.create_temp_guard().unwrap();
+        let reader = guard.reader();
+        // Keep a read side open so the writer can connect and its bytes stay buffered
+        let _read_stream = reader.open_stream().unwrap();
+        let mut connected = guard.writer().connect().await.unwrap();
+        connected.write(b"garbage bytes").await.unwrap();
+        assert_eq!(reader.drain().await.unwrap(), 13);
+        assert_eq!(reader.drain().await.unwrap(), 0);
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn raw_fds() -> io::Result<()> {
+        use nix::fcntl::{fcntl, FcntlArg};
+        use std::os::unix::io::AsRawFd;
+        use tokio::io::AsyncWriteExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_44");
+        let guard = pipe.create_temp_guard().unwrap();
+        let read_stream = guard.reader().open_stream().unwrap();
+        let mut write_stream = guard.writer().open_stream().unwrap();
+        let connected = guard.writer().connect().await.unwrap();
+        for fd in [
+            read_stream.as_raw_fd(),
+            write_stream.as_raw_fd(),
+            connected.as_raw_fd(),
+        ] {
+            assert!(fcntl(fd, FcntlArg::F_GETFD).is_ok());
+        }
+        write_stream.shutdown().await?;
+        assert_eq!(write_stream.as_raw_fd(), -1);
+        Ok(())
+    }
+
+    #[cfg(all(feature = "bytes", feature = "async"))]
+    #[tokio::test]
+    async fn bytes_round_trip() -> io::Result<()> {
+        use bytes::Bytes;
+        let pipe = super::Pipe::new("/tmp/test_pipe_45");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let reader = guard.reader();
+        let data_to_send = Bytes::from_static(b"Hello pipe");
+        let t1 = task::spawn(async move { writer.async_write_bytes(data_to_send).await });
+        let t2 = task::spawn(async move { reader.async_read_bytes().await });
+        t1.await?.unwrap();
+        assert_eq!(t2.await?.unwrap(), Bytes::from_static(b"Hello pipe"));
+        let writer = guard.writer();
+        let reader = guard.reader();
+        let t_write = std::thread::spawn(move || writer.write_bytes(Bytes::from_static(b"sync")));
+        let t_read = std::thread::spawn(move || reader.read_bytes());
+        t_write.join().unwrap().unwrap();
+        assert_eq!(t_read.join().unwrap().unwrap(), Bytes::from_static(b"sync"));
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn cloexec_handles() -> io::Result<()> {
+        use std::os::unix::io::AsRawFd;
+        use std::process::Command;
+        // `test` is a shell builtin, so /proc/self is the exec'ed shell
+        let inherited = |fd: i32| {
+            Command::new("sh")
+                .arg("-c")
+                .arg(format!("test -e /proc/self/fd/{}", fd))
+                .status()
+                .unwrap()
+                .success()
+        };
+        let pipe = super::Pipe::new("/tmp/test_pipe_46");
+        let guard = pipe.create_temp_guard().unwrap();
+        let read_stream = guard.reader().open_stream().unwrap();
+        assert!(!inherited(read_stream.as_raw_fd()));
+        let pipe = super::Pipe::builder("/tmp/test_pipe_46")
+            .cloexec(false)
+            .build();
+        let read_stream = pipe.reader().open_stream().unwrap();
+        assert!(inherited(read_stream.as_raw_fd()));
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn write_retry_after_reader_restart() -> io::Result<()> {
+        use std::time::Duration;
+        use tokio::io::AsyncReadExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_47");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let mut read_stream = guard.reader().open_stream().unwrap();
+        writer
+            .write_retry(b"first", 0, Duration::ZERO)
+            .await
+            .unwrap();
+        let mut received = Vec::new();
+        read_stream.read_to_end(&mut received).await?;
+        assert_eq!(received, b"first");
+        drop(read_stream);
+        let reader = guard.reader();
+        let t2 = task::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let mut read_stream = reader.open_stream()?;
+            let mut received = Vec::new();
+            read_stream.read_to_end(&mut received).await?;
+            super::Result::Ok(received)
+        });
+        writer
+            .write_retry(b"second", 20, Duration::from_millis(10))
+            .await
+            .unwrap();
+        assert_eq!(t2.await?.unwrap(), b"second");
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn write_without_reader_is_epipe() -> io::Result<()> {
+        use tokio::io::AsyncWriteExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_48");
+        let guard = pipe.create_temp_guard().unwrap();
+        let read_stream = guard.reader().open_stream().unwrap();
+        let mut write_stream = guard.writer().open_stream().unwrap();
+        drop(read_stream);
+        let err = write_stream.write_all(b"Hello pipe").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn write_from_cursor() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_49");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let reader = guard.reader();
+        let data_to_send: Vec<u8> = (0..100 * 1024).map(|i| (i % 253) as u8).collect();
+        let expected = data_to_send.clone();
+        let t1 =
+            task::spawn(async move { writer.write_from(std::io::Cursor::new(data_to_send)).await });
+        let t2 = task::spawn(async move { reader.async_read().await });
+        assert_eq!(t1.await?.unwrap(), 100 * 1024);
+        assert_eq!(t2.await?.unwrap(), expected);
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn copy_to_cursor() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_50");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let reader = guard.reader();
+        let data_to_send: Vec<u8> = (0..100 * 1024).map(|i| (i % 247) as u8).collect();
+        let expected = data_to_send.clone();
+        let t1 = task::spawn(async move { writer.async_write(&data_to_send).await });
+        let mut sink = std::io::Cursor::new(Vec::new());
+        let copied = reader.copy_to(&mut sink).await.unwrap();
+        t1.await?.unwrap();
+        assert_eq!(copied, expected.len() as u64);
+        assert_eq!(sink.into_inner(), expected);
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn path_encoding() -> io::Result<()> {
+        use super::PipeError;
+        use std::ffi::OsString;
+        use std::os::unix::ffi::OsStringExt;
+        let non_utf8 = OsString::from_vec(b"/tmp/test_pipe_51_\xff".to_vec());
+        let pipe = super::Pipe::new(non_utf8);
+        assert!(matches!(
+            pipe.writer().write(b"Hello pipe"),
+            Err(PipeError::PathIo { .. })
+        ));
+        pipe.ensure_exists().unwrap();
+        assert!(pipe.is_fifo().unwrap());
+        pipe.delete().await.unwrap();
+        let with_nul = super::Pipe::new("/tmp/test_pipe_51\0nul");
+        assert!(matches!(
+            with_nul.ensure_exists(),
+            Err(PipeError::InvalidPath(_))
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn read_sessions_in_loop() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_52");
+        let guard = pipe.create_temp_guard().unwrap();
+        let reader = guard.reader();
+        for session in ["first", "second"] {
+            let writer = guard.writer();
+            let t1 = task::spawn(async move { writer.async_write_str(session).await });
+            assert_eq!(reader.read_session().await.unwrap(), session.as_bytes());
+            t1.await?.unwrap();
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn read_until_nul() -> io::Result<()> {
+        use crate::stream::Record;
+        let pipe = super::Pipe::new("/tmp/test_pipe_53");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let mut records = guard.reader().record_reader().unwrap();
+        let t1 = task::spawn(async move { writer.async_write(b"one\0two\0tail").await });
+        assert_eq!(
+            records.read_until(0, false).await.unwrap(),
+            Record::Complete(b"one".to_vec())
+        );
+        assert_eq!(
+            records.read_until(0, true).await.unwrap(),
+            Record::Complete(b"two\0".to_vec())
+        );
+        assert_eq!(
+            records.read_until(0, false).await.unwrap(),
+            Record::Eof(b"tail".to_vec())
+        );
+        t1.await?.unwrap();
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn streams_from_raw_fds() -> io::Result<()> {
+        use crate::stream::{PipeReadStream, PipeWriteStream};
+        use std::os::unix::io::FromRawFd;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let (read_fd, write_fd) = nix::unistd::pipe().unwrap();
+        let mut read_stream = unsafe { PipeReadStream::from_raw_fd(read_fd) };
+        let mut write_stream = unsafe { PipeWriteStream::from_raw_fd(write_fd) };
+        write_stream.write_all(b"Hello pipe").await?;
+        write_stream.shutdown().await?;
+        let mut received = Vec::new();
+        read_stream.read_to_end(&mut received).await?;
+        assert_eq!(received, b"Hello pipe");
+        Ok(())
+    }
+
+    #[test]
+    fn new_temp_is_unique() {
+        let first = super::Pipe::new_temp()
+            .unwrap()
+            .create_temp_guard()
+            .unwrap();
+        let second = super::Pipe::new_temp()
+            .unwrap()
+            .create_temp_guard()
+            .unwrap();
+        assert_ne!(first.as_ref(), second.as_ref());
+        assert!(first.is_fifo().unwrap());
+        assert!(second.is_fifo().unwrap());
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn stream_with_capacity() -> io::Result<()> {
+        use tokio::io::AsyncReadExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_54");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let mut read_stream = guard.reader().open_stream_with_capacity(64 * 1024).unwrap();
+        let data_to_send: Vec<u8> = (0..4 * 1024 * 1024).map(|i| (i % 241) as u8).collect();
+        let expected = data_to_send.clone();
+        let t1 = task::spawn(async move { writer.async_write(&data_to_send).await });
+        let mut received = Vec::new();
+        read_stream.read_to_end(&mut received).await?;
+        t1.await?.unwrap();
+        assert_eq!(received, expected);
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn copy_to_reports_progress() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_55");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let reader = guard.reader();
+        let data_to_send = vec![7u8; 300 * 1024];
+        let t1 = task::spawn(async move { writer.async_write(&data_to_send).await });
+        let mut totals = Vec::new();
+        let mut sink = Vec::new();
+        let copied = reader
+            .copy_to_with_progress(&mut sink, |total| totals.push(total))
+            .await
+            .unwrap();
+        t1.await?.unwrap();
+        assert_eq!(copied, 300 * 1024);
+        assert!(totals.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(totals.last(), Some(&copied));
+        assert_eq!(sink.len(), 300 * 1024);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_mode_repairs_permissions() {
+        let _mode_lock = MODE_LOCK.blocking_lock();
+        use nix::sys::stat::{stat, Mode};
+        use std::os::unix::fs::PermissionsExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_56");
+        let guard = pipe.create_temp_guard().unwrap();
+        // Same outcome as creating the pipe with 0o660 under a 0o027 umask
+        std::fs::set_permissions("/tmp/test_pipe_56", std::fs::Permissions::from_mode(0o640))
+            .unwrap();
+        guard.ensure_mode(Mode::from_bits_truncate(0o660)).unwrap();
+        let st = stat("/tmp/test_pipe_56").unwrap();
+        assert_eq!(st.st_mode & 0o777, 0o660);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn exact_mode_under_umask() -> io::Result<()> {
+        let _mode_lock = MODE_LOCK.lock().await;
+        use nix::sys::stat::{self, Mode};
+        // The umask is process-wide, so the test runs under whatever is set rather than changing
+        // it under the feet of parallel tests. Any common umask masks some bits of 0o666
+        let pipe = super::Pipe::with_mode("/tmp/test_pipe_57", Mode::from_bits_truncate(0o666));
+        pipe.ensure_exists().unwrap();
+        let st = stat::stat("/tmp/test_pipe_57").unwrap();
+        assert_eq!(st.st_mode & 0o777, 0o666);
+        pipe.delete().await.unwrap();
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn flush_makes_data_readable() -> io::Result<()> {
+        use std::time::Duration;
+        use tokio::io::AsyncReadExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_58");
+        pipe.ensure_exists().unwrap();
+        let mut read_stream = pipe.reader().open_stream().unwrap();
+        let mut connected = pipe.writer().connect().await.unwrap();
+        connected.write(b"sync").await.unwrap();
+        connected.flush().await.unwrap();
+        let mut buf = [0; 4];
+        tokio::time::timeout(Duration::from_secs(1), read_stream.read_exact(&mut buf)).await??;
+        assert_eq!(&buf, b"sync");
+        Ok(pipe.delete().await?)
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn write_line_round_trip() -> io::Result<()> {
+        use futures::TryStreamExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_59");
+        pipe.ensure_exists().unwrap();
+        let writer = pipe.writer();
+        let lines = pipe.reader().lines().unwrap();
+        let t1 = task::spawn(async move {
+            let mut connected = writer.connect().await?;
+            connected.write_line("first").await?;
+            connected.write_line("second\n").await?;
+            connected.write_line("").await
+        });
+        let read_result: Vec<String> = lines.try_collect().await?;
+        t1.await?.unwrap();
+        assert_eq!(read_result, ["first", "second", ""]);
+
+        let writer = pipe.writer();
+        let reader = pipe.reader();
+        let t1 = task::spawn(async move { writer.async_write_line("single\n").await });
+        assert_eq!(reader.async_read_str().await.unwrap(), "single\n");
+        t1.await?.unwrap();
+        Ok(pipe.delete().await?)
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn write_records_in_one_batch() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_95");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let reader = guard.reader();
+        let t1 = task::spawn(async move {
+            writer
+                .async_write_records(&["first", "second", "third"], b'\n')
+                .await
+        });
+        assert_eq!(
+            reader.async_read_str().await.unwrap(),
+            "first\nsecond\nthird\n"
+        );
+        t1.await?.unwrap();
+
+        let writer = guard.writer();
+        let t1 = task::spawn_blocking(move || writer.write_records(&["a", "", "b"], 0));
+        assert_eq!(reader.async_read().await.unwrap(), b"a\0\0b\0");
+        t1.await?.unwrap();
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn errors_name_the_pipe() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_60");
+        let err = pipe.reader().async_read().await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "failed to read from FIFO /tmp/test_pipe_60: No such file or directory (os error 2)"
+        );
+        let err = pipe.writer().write(b"Hello pipe").unwrap_err();
+        assert!(err.to_string().contains("/tmp/test_pipe_60"));
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::NotFound);
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn write_vectored_larger_than_pipe() -> io::Result<()> {
+        use std::io::IoSlice;
+        use tokio::io::AsyncReadExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_111");
+        let guard = pipe.create_temp_guard().unwrap();
+        let mut read_stream = guard.reader().open_stream().unwrap();
+        let mut connected = guard.writer().connect().await.unwrap();
+        let head = vec![1u8; 200 * 1024];
+        let body = vec![2u8; 300 * 1024];
+        let t1 = task::spawn(async move {
+            let mut received = Vec::new();
+            read_stream
+                .read_to_end(&mut received)
+                .await
+                .map(|_| received)
+        });
+        let written = connected
+            .write_vectored(&[IoSlice::new(&head), IoSlice::new(&[]), IoSlice::new(&body)])
+            .await
+            .unwrap();
+        assert_eq!(written, head.len() + body.len());
+        drop(connected);
+        assert_eq!(t1.await??, [head, body].concat());
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn write_vectored_in_order() -> io::Result<()> {
+        use std::io::IoSlice;
+        use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt};
+        let pipe = super::Pipe::new("/tmp/test_pipe_61");
+        let guard = pipe.create_temp_guard().unwrap();
+        let mut read_stream = guard.reader().open_stream().unwrap();
+        let mut connected = guard.writer().connect().await.unwrap();
+        let written = connected
+            .write_vectored(&[IoSlice::new(b"head:"), IoSlice::new(b"body;")])
+            .await
+            .unwrap();
+        assert_eq!(written, 10);
+        drop(connected);
+        let mut write_stream = guard.writer().open_stream().unwrap();
+        assert!(write_stream.is_write_vectored());
+        let written = write_stream
+            .write_vectored(&[IoSlice::new(b"more:"), IoSlice::new(b"data")])
+            .await?;
+        assert_eq!(written, 9);
+        write_stream.shutdown().await?;
+        let mut received = String::new();
+        read_stream.read_to_string(&mut received).await?;
+        assert_eq!(received, "head:body;more:data");
+        Ok(())
+    }
+
+    #[test]
+    fn connect_blocking_many_writes() {
+        use std::io::Write;
+        use std::thread;
+        let pipe = super::Pipe::new("/tmp/test_pipe_62");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let t_write = thread::spawn(move || -> super::Result<()> {
+            let mut connected = writer.connect_blocking()?;
+            for i in 0..50 {
+                write!(connected, "{};", i)?;
+            }
+            Ok(())
+        });
+        let read_result = guard.reader().string().unwrap();
+        t_write.join().unwrap().unwrap();
+        let expected: String = (0..50).map(|i| format!("{};", i)).collect();
+        assert_eq!(read_result, expected);
+    }
+
+    #[test]
+    fn open_blocking_reads_lines() {
+        use std::io::BufRead;
+        use std::thread;
+        let pipe = super::Pipe::new("/tmp/test_pipe_63");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let t_write = thread::spawn(move || writer.write_str("first\nsecond\n".to_string()));
+        let mut stream = guard.reader().open_blocking().unwrap();
+        let mut line = String::new();
+        stream.read_line(&mut line).unwrap();
+        assert_eq!(line, "first\n");
+        let rest: Vec<String> = stream.lines().collect::<std::io::Result<_>>().unwrap();
+        t_write.join().unwrap().unwrap();
+        assert_eq!(rest, ["second"]);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn read_opt_empty_session() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_64");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let reader = guard.reader();
+        let t1 = task::spawn(async move { writer.connect().await.map(drop) });
+        assert_eq!(reader.async_read_opt().await.unwrap(), None);
+        t1.await?.unwrap();
+        let writer = guard.writer();
+        let t1 = task::spawn(async move { writer.async_write(b"Hello pipe").await });
+        assert_eq!(
+            reader.async_read_opt().await.unwrap(),
+            Some(b"Hello pipe".to_vec())
+        );
+        t1.await?.unwrap();
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn delete_only_fifos() -> io::Result<()> {
+        use super::PipeError;
+        std::fs::write("/tmp/test_pipe_65", b"not a pipe")?;
+        let pipe = super::Pipe::new("/tmp/test_pipe_65");
+        assert!(matches!(
+            pipe.try_delete().await,
+            Err(PipeError::NotAFifo(_))
+        ));
+        assert!(matches!(
+            pipe.clone().delete().await,
+            Err(PipeError::NotAFifo(_))
+        ));
+        assert!(std::path::Path::new("/tmp/test_pipe_65").exists());
+        pipe.clone().force_delete().await.unwrap();
+        assert!(!pipe.exists());
+        pipe.force_delete().await.unwrap();
+        Ok(())
+    }
+
+    #[cfg(all(feature = "async", feature = "tracing"))]
+    #[tokio::test]
+    async fn buffered_writer_warns_on_drop() -> io::Result<()> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use tracing::span::{Attributes, Id, Record};
+        /// Counts the warnings logged while it is the default subscriber
+        struct Warnings(Arc<AtomicUsize>);
+        impl tracing::Subscriber for Warnings {
+            fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, _: &Attributes<'_>) -> Id {
+                Id::from_u64(1)
+            }
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, event: &tracing::Event<'_>) {
+                if *event.metadata().level() == tracing::Level::WARN {
+                    self.0.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+        let pipe = super::Pipe::new("/tmp/test_pipe_101");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let reader = guard.reader();
+        let warnings = Arc::new(AtomicUsize::new(0));
+        let counter = warnings.clone();
+        let t1 = task::spawn(async move {
+            let mut buffered = super::BufferedWriter::new(writer.connect().await?);
+            buffered.write_str("pending").await?;
+            tracing::subscriber::with_default(Warnings(counter), || drop(buffered));
+            super::Result::Ok(())
+        });
+        assert_eq!(reader.async_read_str().await.unwrap(), "pending");
+        t1.await?.unwrap();
+        assert_eq!(warnings.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn buffered_writer_batches() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_66");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let reader = guard.reader();
+        let t1 = task::spawn(async move {
+            let mut buffered = super::BufferedWriter::with_capacity(64, writer.connect().await?);
+            for i in 0..100 {
+                buffered.write_str(&format!("{};", i)).await?;
+            }
+            buffered.write(&[b'x'; 100]).await?;
+            buffered.write_str("tail").await?;
+            // The remaining bytes are written out on drop
+            super::Result::Ok(())
+        });
+        let read_result = reader.async_read_str().await.unwrap();
+        t1.await?.unwrap();
+        let mut expected: String = (0..100).map(|i| format!("{};", i)).collect();
+        expected.push_str(&"x".repeat(100));
+        expected.push_str("tail");
+        assert_eq!(read_result, expected);
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn read_frame_timeout_keeps_partial() -> io::Result<()> {
+        use super::PipeError;
+        use std::time::Duration;
+        let pipe = super::Pipe::new("/tmp/test_pipe_67");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let reader = guard.reader();
+        let t1 = task::spawn(async move {
+            let mut connected = writer.connect().await?;
+            // Header announces 10 bytes, only half of them arrive before the deadline
+            connected
+                .write(&[1, 0, 0, 0, 10, b'h', b'e', b'l', b'l', b'o'])
+                .await?;
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            super::Result::Ok(())
+        });
+        match reader.read_frame_timeout(Duration::from_millis(100)).await {
+            Err(PipeError::Timeout(partial)) => assert_eq!(partial, b"\x01\0\0\0\x0ahello"),
+            other => panic!("expected Timeout, got {:?}", other),
+        }
+        t1.await?.unwrap();
+        let writer = guard.writer();
+        let t1 = task::spawn(async move { writer.async_write_frame(b"whole").await });
+        let frame = reader
+            .read_frame_timeout(Duration::from_secs(5))
+            .await
+            .unwrap();
+        t1.await?.unwrap();
+        assert_eq!(frame, b"whole");
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn read_into_reused_buffer() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_68");
+        let guard = pipe.create_temp_guard().unwrap();
+        let reader = guard.reader();
+        let mut buf = Vec::new();
+        for msg in ["first message", "second"] {
+            let writer = guard.writer();
+            let t1 = task::spawn(async move { writer.async_write(msg.as_bytes()).await });
+            buf.clear();
+            let read = reader.async_read_into(&mut buf).await.unwrap();
+            t1.await?.unwrap();
+            assert_eq!(read, msg.len());
+            assert_eq!(buf, msg.as_bytes());
+        }
+        let writer = guard.writer();
+        let t1 = task::spawn(async move { writer.async_write(b"sync").await });
+        let reader = guard.reader();
+        buf.clear();
+        let read = task::spawn_blocking(move || reader.read_into(&mut buf).map(|n| (n, buf)))
+            .await?
+            .unwrap();
+        t1.await?.unwrap();
+        assert_eq!(read, (4, b"sync".to_vec()));
+        Ok(())
+    }
+
+    #[test]
+    fn open_blocking_and_non_blocking() {
+        use super::PipeError;
+        use std::io::{Read, Write};
+        use std::thread;
+        let pipe = super::Pipe::new("/tmp/test_pipe_69");
+        let guard = pipe.create_temp_guard().unwrap();
+        assert!(matches!(
+            guard.writer().open(true),
+            Err(PipeError::NoReader(_))
+        ));
+        let mut read_side = guard.reader().open(true).unwrap();
+        let mut buf = [0u8; 16];
+        assert_eq!(read_side.read(&mut buf).unwrap(), 0);
+        let mut write_side = guard.writer().open(true).unwrap();
+        assert_eq!(
+            read_side.read(&mut buf).unwrap_err().kind(),
+            std::io::ErrorKind::WouldBlock
+        );
+        write_side.write_all(b"nonblocking").unwrap();
+        assert_eq!(read_side.read(&mut buf).unwrap(), 11);
+        drop((read_side, write_side));
+
+        let writer = guard.writer();
+        let t_write = thread::spawn(move || -> super::Result<()> {
+            writer.open(false)?.write_all(b"blocking")?;
+            Ok(())
+        });
+        let mut received = String::new();
+        guard
+            .reader()
+            .open(false)
+            .unwrap()
+            .read_to_string(&mut received)
+            .unwrap();
+        t_write.join().unwrap().unwrap();
+        assert_eq!(received, "blocking");
+    }
+
+    #[test]
+    fn status_snapshot() {
+        let _mode_lock = MODE_LOCK.blocking_lock();
+        use nix::sys::stat::Mode;
+        use std::io::Write;
+        let pipe = super::Pipe::with_mode("/tmp/test_pipe_70", Mode::from_bits_truncate(0o640));
+        let status = pipe.status().unwrap();
+        assert!(!status.exists && !status.is_fifo);
+        assert_eq!(status.buffered, None);
+        let guard = pipe.create_temp_guard().unwrap();
+        let _read_side = guard.reader().open(true).unwrap();
+        guard
+            .writer()
+            .open(true)
+            .unwrap()
+            .write_all(b"Hello pipe")
+            .unwrap();
+        let status = guard.status().unwrap();
+        assert!(status.exists && status.is_fifo);
+        assert_eq!(status.mode, 0o640);
+        assert_eq!(status.buffered, Some(10));
+        #[cfg(target_os = "linux")]
+        assert_eq!(status.capacity, Some(guard.capacity().unwrap()));
+    }
+
+    #[test]
+    fn bytes_available_before_read() {
+        use std::io::{Read, Write};
+        let pipe = super::Pipe::new("/tmp/test_pipe_71");
+        let guard = pipe.create_temp_guard().unwrap();
+        let reader = guard.reader();
+        let mut read_side = reader.open(true).unwrap();
+        assert_eq!(reader.bytes_available().unwrap(), 0);
+        let mut write_side = guard.writer().open(true).unwrap();
+        write_side.write_all(&[7u8; 1000]).unwrap();
+        assert_eq!(reader.bytes_available().unwrap(), 1000);
+        let mut buf = [0u8; 400];
+        read_side.read_exact(&mut buf).unwrap();
+        assert_eq!(reader.bytes_available().unwrap(), 600);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn broadcast_skips_missing_reader() -> io::Result<()> {
+        use super::PipeError;
+        use tokio::io::AsyncReadExt;
+        let pipes: Vec<_> = (72..75)
+            .map(|i| super::Pipe::new(format!("/tmp/test_pipe_{}", i)))
+            .collect();
+        let guards: Vec<_> = pipes
+            .iter()
+            .map(|pipe| pipe.create_temp_guard().unwrap())
+            .collect();
+        let mut first = guards[0].reader().open_stream().unwrap();
+        let mut third = guards[2].reader().open_stream().unwrap();
+        let results = super::broadcast(&pipes, b"Hello pipes").await;
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(PipeError::NoReader(_))));
+        assert!(results[2].is_ok());
+        for stream in [&mut first, &mut third] {
+            let mut received = Vec::new();
+            stream.read_to_end(&mut received).await?;
+            assert_eq!(received, b"Hello pipes");
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn connect_timeout_without_reader() -> io::Result<()> {
+        use super::PipeError;
+        use std::time::Duration;
+        use tokio::io::AsyncReadExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_75");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        match writer.connect_timeout(Duration::from_millis(50)).await {
+            Err(PipeError::Timeout(partial)) => assert!(partial.is_empty()),
+            other => panic!("expected Timeout, got {:?}", other.map(|_| ())),
+        }
+        let mut read_stream = guard.reader().open_stream().unwrap();
+        let mut connected = writer
+            .connect_timeout(Duration::from_secs(1))
+            .await
+            .unwrap();
+        connected.write(b"Hello pipe").await.unwrap();
+        drop(connected);
+        let mut received = Vec::new();
+        read_stream.read_to_end(&mut received).await?;
+        assert_eq!(received, b"Hello pipe");
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn custom_codec_round_trip() -> io::Result<()> {
+        use super::Codec;
+        use crate::codec::LengthPrefix;
+
+        /// Sends numbers as comma separated text
+        struct Csv;
+
+        impl Codec<Vec<u32>> for Csv {
+            fn encode(&self, value: &Vec<u32>) -> super::Result<Vec<u8>> {
+                let text: Vec<String> = value.iter().map(u32::to_string).collect();
+                Ok(text.join(",").into_bytes())
+            }
+            fn decode(&self, data: &[u8]) -> super::Result<Vec<u32>> {
+                String::from_utf8_lossy(data)
+                    .split(',')
+                    .map(|n| {
+                        n.parse()
+                            .map_err(|_| io::Error::from(io::ErrorKind::InvalidData).into())
+                    })
+                    .collect()
+            }
+        }
+
+        let pipe = super::Pipe::new("/tmp/test_pipe_76");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let t1 = task::spawn(async move { writer.async_write_with(&Csv, &vec![1, 20, 300]).await });
+        assert_eq!(
+            guard.reader().async_read_with(&Csv).await.unwrap(),
+            [1, 20, 300]
+        );
+        t1.await?.unwrap();
+        let writer = guard.writer();
+        let t1 = task::spawn(async move {
+            writer
+                .async_write_with(&LengthPrefix, &b"framed".to_vec())
+                .await
+        });
+        assert_eq!(
+            guard.reader().async_read_with(&LengthPrefix).await.unwrap(),
+            b"framed"
+        );
+        t1.await?.unwrap();
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn recreate_replaces_pipe() -> io::Result<()> {
+        let _mode_lock = MODE_LOCK.lock().await;
+        use nix::sys::stat::{stat, Mode};
+        let pipe = super::Pipe::new("/tmp/test_pipe_77");
+        pipe.recreate(Some(Mode::from_bits_truncate(0o600)))
+            .unwrap();
+        let before = stat("/tmp/test_pipe_77").unwrap();
+        assert!(pipe.is_fifo().unwrap());
+        assert_eq!(before.st_mode & 0o777, 0o600);
+        pipe.recreate(Some(Mode::from_bits_truncate(0o640)))
+            .unwrap();
+        let after = stat("/tmp/test_pipe_77").unwrap();
+        assert_eq!(after.st_mode & 0o777, 0o640);
+        assert_ne!(before.st_ino, after.st_ino);
+        Ok(pipe.delete().await?)
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test(flavor = "current_thread")]
+    async fn ensure_exists_async_current_thread() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_78");
+        pipe.ensure_exists_async().await.unwrap();
+        assert!(pipe.is_fifo().unwrap());
+        pipe.ensure_exists_async().await.unwrap();
+        assert!(pipe.try_delete().await.unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn scan_dir_finds_fifos() {
+        let dir = std::path::Path::new("/tmp/test_pipe_dir_79");
+        let _ = std::fs::remove_dir_all(dir);
+        std::fs::create_dir_all(dir.join("subdir")).unwrap();
+        std::fs::write(dir.join("regular"), b"not a pipe").unwrap();
+        super::Pipe::new(dir.join("b.fifo"))
+            .ensure_exists()
+            .unwrap();
+        super::Pipe::new(dir.join("a.fifo"))
+            .ensure_exists()
+            .unwrap();
+        let found: Vec<String> = super::Pipe::scan_dir(dir)
+            .unwrap()
+            .iter()
+            .map(|pipe| pipe.to_string())
+            .collect();
+        assert_eq!(
+            found,
+            [
+                "/tmp/test_pipe_dir_79/a.fifo",
+                "/tmp/test_pipe_dir_79/b.fifo"
+            ]
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn open_retry_waits_for_pipe() -> io::Result<()> {
+        use std::time::Duration;
+        use tokio::io::AsyncReadExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_80");
+        let reader = pipe.reader();
+        let err = reader
+            .open_retry(2, Duration::from_millis(5))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::NotFound);
+        let creator = pipe.clone();
+        let t1 = task::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            creator.ensure_exists()
+        });
+        let mut stream = reader
+            .open_retry(100, Duration::from_millis(10))
+            .await
+            .unwrap();
+        t1.await?.unwrap();
+        let writer = pipe.writer();
+        let t1 = task::spawn(async move { writer.async_write(b"Hello pipe").await });
+        let mut received = Vec::new();
+        stream.read_to_end(&mut received).await?;
+        t1.await?.unwrap();
+        assert_eq!(received, b"Hello pipe");
+        Ok(pipe.delete().await?)
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn checked_frame_round_trip() -> io::Result<()> {
+        use super::PipeError;
+        let pipe = super::Pipe::new("/tmp/test_pipe_81");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let t1 = task::spawn(async move { writer.async_write_frame_checked(b"Hello pipe").await });
+        let frame = guard.reader().async_read_frame_checked().await.unwrap();
+        t1.await?.unwrap();
+        assert_eq!(frame, b"Hello pipe");
+        // Length 5, the CRC32 of "hello", but the payload arrives with a flipped bit
+        let mut corrupted = vec![0, 0, 0, 5, 0x36, 0x10, 0xa6, 0x86];
+        corrupted.extend_from_slice(b"hellp");
+        let writer = guard.writer();
+        let t1 = task::spawn(async move { writer.async_write(&corrupted).await });
+        match guard.reader().async_read_frame_checked().await {
+            Err(PipeError::ChecksumMismatch { expected, .. }) => assert_eq!(expected, 0x3610a686),
+            other => panic!("expected ChecksumMismatch, got {:?}", other),
+        }
+        t1.await?.unwrap();
+        Ok(())
+    }
+
+    #[cfg(feature = "tokio-util")]
+    #[tokio::test]
+    async fn read_cancelled_without_writer() -> io::Result<()> {
+        use std::time::Duration;
+        use tokio_util::sync::CancellationToken;
+        let pipe = super::Pipe::new("/tmp/test_pipe_104");
+        let guard = pipe.create_temp_guard().unwrap();
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        task::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            canceller.cancel();
+        });
+        assert_eq!(
+            guard.reader().async_read_cancellable(token).await.unwrap(),
+            None
+        );
+        let writer = guard.writer();
+        let t1 = task::spawn(async move { writer.async_write(b"Hello pipe").await });
+        let data = guard
+            .reader()
+            .async_read_cancellable(CancellationToken::new())
+            .await
+            .unwrap();
+        t1.await?.unwrap();
+        assert_eq!(data.as_deref(), Some(&b"Hello pipe"[..]));
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn frame_version_mismatch() -> io::Result<()> {
+        use super::PipeError;
+        let pipe = super::Pipe::new("/tmp/test_pipe_103");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let t1 = task::spawn(async move { writer.async_write(&[2, 0, 0, 0, 1, b'x']).await });
+        match guard.reader().async_read_frame().await {
+            Err(PipeError::UnsupportedVersion(version)) => assert_eq!(version, 2),
+            other => panic!("expected UnsupportedVersion, got {:?}", other),
+        }
+        t1.await?.unwrap();
+        let writer = guard.writer();
+        let t1 = task::spawn(async move { writer.async_write_frame_unversioned(b"legacy").await });
+        let frame = guard.reader().async_read_frame_unversioned().await.unwrap();
+        t1.await?.unwrap();
+        assert_eq!(frame, b"legacy");
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn write_and_wait_drained_slow_reader() -> io::Result<()> {
+        use std::time::{Duration, Instant};
+        use tokio::io::AsyncReadExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_82");
+        let guard = pipe.create_temp_guard().unwrap();
+        let mut read_stream = guard.reader().open_stream().unwrap();
+        let mut connected = guard.writer().connect().await.unwrap();
+        let t_read = task::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let mut buf = [0u8; 10];
+            read_stream.read_exact(&mut buf).await.map(|_| buf)
+        });
+        let start = Instant::now();
+        connected
+            .write_and_wait_drained(b"Hello pipe")
+            .await
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert_eq!(&t_read.await??, b"Hello pipe");
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn read_on_blocking_pool() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_83");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let t1 = task::spawn(async move { writer.async_write(b"Hello pipe").await });
+        let read_result = guard.reader().async_read_blocking().await.unwrap();
+        t1.await?.unwrap();
+        assert_eq!(read_result, b"Hello pipe");
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn invalid_utf8_strict_and_lossy() -> io::Result<()> {
+        use super::PipeError;
+        let pipe = super::Pipe::new("/tmp/test_pipe_84");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let t1 = task::spawn(async move { writer.async_write(b"ok \xff\xfe end").await });
+        match guard.reader().async_read_str().await {
+            Err(PipeError::Utf8(err)) => {
+                assert_eq!(err.utf8_error().valid_up_to(), 3);
+                assert_eq!(err.into_bytes(), b"ok \xff\xfe end");
+            }
+            other => panic!("expected Utf8, got {:?}", other),
+        }
+        t1.await?.unwrap();
+        let writer = guard.writer();
+        let t1 = task::spawn(async move { writer.async_write(b"ok \xff\xfe end").await });
+        let read_result = guard.reader().async_read_lossy().await.unwrap();
+        t1.await?.unwrap();
+        assert_eq!(read_result, "ok \u{fffd}\u{fffd} end");
+        Ok(())
+    }
+
+    #[cfg(all(target_os = "linux", feature = "async"))]
+    #[tokio::test]
+    async fn open_pair_round_trip() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_85");
+        let (mut reader, mut writer) = pipe.open_pair().unwrap();
+        let encode = |data: &[u8]| crate::frame::encode_versioned(data).unwrap();
+        writer.write(&encode(b"request")).await.unwrap();
+        assert_eq!(reader.read_frame().await.unwrap(), b"request");
+        writer.write(&encode(b"second")).await.unwrap();
+        assert_eq!(reader.read_frame().await.unwrap(), b"second");
+        Ok(pipe.delete().await?)
+    }
+
+    #[cfg(all(target_os = "linux", feature = "async"))]
+    #[tokio::test]
+    async fn persistent_reader_shutdown() -> io::Result<()> {
+        use futures::StreamExt;
+        use std::time::Duration;
+        let pipe = super::Pipe::new("/tmp/test_pipe_86");
+        let guard = pipe.create_temp_guard().unwrap();
+        let mut reader = guard.reader().open_persistent().unwrap();
+        let handle = reader.shutdown_handle();
+        let stopper = handle.clone();
+        let t1 = task::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            stopper.shutdown();
+        });
+        let err = reader.read_frame().await.unwrap_err();
+        assert_eq!(
+            io::Error::from(err).kind(),
+            io::ErrorKind::ConnectionAborted
+        );
+        t1.await?;
+        assert!(handle.is_shutdown());
+
+        let reader = guard.reader().open_persistent().unwrap();
+        let handle = reader.shutdown_handle();
+        let mut lines = reader.lines();
+        guard.writer().async_write(b"first\n").await.unwrap();
+        assert_eq!(lines.next().await.unwrap()?, "first");
+        let t1 = task::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            handle.shutdown();
+        });
+        let next = tokio::time::timeout(Duration::from_secs(1), lines.next()).await?;
+        assert!(next.is_none());
+        t1.await?;
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn messages_one_per_writer() -> io::Result<()> {
+        use futures::StreamExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_87");
+        let guard = pipe.create_temp_guard().unwrap();
+        let reader = guard.reader();
+        let writer = guard.writer();
+        let t1 = task::spawn(async move { writer.async_write(b"single").await });
+        assert_eq!(reader.read_message().await.unwrap(), b"single");
+        t1.await?.unwrap();
+        let messages = reader.messages();
+        futures::pin_mut!(messages);
+        for msg in ["first", "second"] {
+            let writer = guard.writer();
+            let t1 = task::spawn(async move { writer.async_write(msg.as_bytes()).await });
+            assert_eq!(messages.next().await.unwrap()?, msg.as_bytes());
+            t1.await?.unwrap();
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn new_temp_in_directory() {
+        let dir = std::path::Path::new("/tmp/test_pipe_dir_88");
+        std::fs::create_dir_all(dir).unwrap();
+        let pipe = super::Pipe::new_temp_in(dir).unwrap();
+        assert!(pipe.as_ref().starts_with(dir));
+        assert!(pipe.is_fifo().unwrap());
+        drop(pipe.create_temp_guard().unwrap());
+        std::fs::remove_dir(dir).unwrap();
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn write_timeout_keeps_partial() -> io::Result<()> {
+        use super::PipeError;
+        use std::time::Duration;
+        let pipe = super::Pipe::new("/tmp/test_pipe_89");
+        let guard = pipe.create_temp_guard().unwrap();
+        // The reader holds the pipe open but never reads, so the buffer fills up
+        let _read_stream = guard.reader().open_stream().unwrap();
+        let mut connected = guard.writer().connect().await.unwrap();
+        let data = vec![7u8; 1024 * 1024];
+        match connected
+            .write_timeout(&data, Duration::from_millis(50))
+            .await
+        {
+            Err(PipeError::Timeout(partial)) => {
+                assert!(!partial.is_empty() && partial.len() < data.len());
+            }
+            other => panic!("expected Timeout, got {:?}", other),
+        }
+        connected
+            .write_timeout(b"", Duration::from_millis(50))
+            .await
+            .unwrap();
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn write_timeout_cancelled() -> io::Result<()> {
+        use io::AsyncReadExt;
+        use nix::fcntl::{fcntl, FcntlArg, OFlag};
+        use std::os::unix::io::AsRawFd;
+        use std::time::Duration;
+        let pipe = super::Pipe::new("/tmp/test_pipe_109");
+        let guard = pipe.create_temp_guard().unwrap();
+        let mut read_stream = guard.reader().open_stream().unwrap();
+        let mut connected = guard.writer().connect().await.unwrap();
+        let data = vec![7u8; 1024 * 1024];
+        // The outer timeout drops the write while it waits for room in the full pipe
+        let write = connected.write_timeout(&data, Duration::from_secs(10));
+        assert!(tokio::time::timeout(Duration::from_millis(50), write)
+            .await
+            .is_err());
+        let flags = OFlag::from_bits_truncate(fcntl(connected.as_raw_fd(), FcntlArg::F_GETFL)?);
+        assert!(!flags.contains(OFlag::O_NONBLOCK));
+        let t1 = task::spawn(async move {
+            let mut received = Vec::new();
+            read_stream
+                .read_to_end(&mut received)
+                .await
+                .map(|_| received)
+        });
+        connected.write(b"after").await.unwrap();
+        drop(connected);
+        assert!(t1.await??.ends_with(b"after"));
+        Ok(())
+    }
+
+    #[test]
+    fn set_nonblocking_on_empty_pipe() {
+        use std::io::{Read, Write};
+        let pipe = super::Pipe::new("/tmp/test_pipe_90");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        let t1 = std::thread::spawn(move || writer.connect_blocking());
+        let mut read_stream = guard.reader().open_blocking().unwrap();
+        let mut connected = t1.join().unwrap().unwrap();
+        read_stream.set_nonblocking(true).unwrap();
+        let mut buffer = [0u8; 16];
+        let err = read_stream.read(&mut buffer).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+        connected.write_all(b"Hello pipe").unwrap();
+        read_stream.set_nonblocking(false).unwrap();
+        let count = read_stream.read(&mut buffer).unwrap();
+        assert_eq!(&buffer[..count], b"Hello pipe");
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn readable_waits_for_data() -> io::Result<()> {
+        use std::time::Duration;
+        use tokio::io::AsyncReadExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_91");
+        let guard = pipe.create_temp_guard().unwrap();
+        let reader = guard.reader();
+        let mut read_stream = reader.open_stream().unwrap();
+        let mut connected = guard.writer().connect().await.unwrap();
+        let pending = tokio::time::timeout(Duration::from_millis(50), reader.readable()).await;
+        assert!(pending.is_err());
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), read_stream.readable())
+                .await
+                .is_err()
+        );
+        connected.write(b"Hello pipe").await.unwrap();
+        reader.readable().await.unwrap();
+        read_stream.readable().await?;
+        let mut buffer = [0u8; 16];
+        let count = read_stream.read(&mut buffer).await?;
+        assert_eq!(&buffer[..count], b"Hello pipe");
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn writable_after_reader_frees_space() -> io::Result<()> {
+        use std::time::Duration;
+        use tokio::io::AsyncReadExt;
+        let pipe = super::Pipe::new("/tmp/test_pipe_92");
+        let guard = pipe.create_temp_guard().unwrap();
+        let mut read_stream = guard.reader().open_stream().unwrap();
+        let mut connected = guard.writer().connect().await.unwrap();
+        connected.writable().await.unwrap();
+        let data = vec![7u8; 1024 * 1024];
+        let partial = match connected
+            .write_timeout(&data, Duration::from_millis(50))
+            .await
+        {
+            Err(super::PipeError::Timeout(partial)) => partial,
+            other => panic!("expected Timeout, got {:?}", other),
+        };
+        let pending = tokio::time::timeout(Duration::from_millis(50), connected.writable()).await;
+        assert!(pending.is_err());
+        let mut buffer = vec![0u8; partial.len()];
+        read_stream.read_exact(&mut buffer).await?;
+        connected.writable().await.unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn set_default_mode_applies_to_new_pipes() {
+        use nix::sys::stat::{stat, Mode};
+        /// Puts the previous default back even if an assertion fails
+        struct RestoreDefault(Mode);
+        impl Drop for RestoreDefault {
+            fn drop(&mut self) {
+                super::set_default_mode(self.0);
+            }
+        }
+        let _mode_lock = MODE_LOCK.blocking_lock();
+        let pipe = super::Pipe::new("/tmp/test_pipe_93");
+        let restore = RestoreDefault(super::default_mode());
+        super::set_default_mode(Mode::from_bits_truncate(0o600));
+        let guard = pipe.create_temp_guard();
+        drop(restore);
+        let guard = guard.unwrap();
+        let st = stat(guard.as_ref()).unwrap();
+        assert_eq!(st.st_mode & 0o777, 0o600);
+        assert_eq!(
+            super::default_mode(),
+            Mode::from_bits_truncate(super::DEFAULT_MODE as _)
+        );
+    }
+
+    #[cfg(all(target_os = "linux", feature = "async"))]
+    #[tokio::test]
+    async fn persistent_reader_reports_writers() -> io::Result<()> {
+        use crate::stream::ConnectionEvent;
+        let pipe = super::Pipe::new("/tmp/test_pipe_94");
+        let guard = pipe.create_temp_guard().unwrap();
+        let (mut reader, mut events) = guard.reader().open_persistent_with_events().unwrap();
+        guard.writer().async_write_frame(b"first").await.unwrap();
+        assert_eq!(reader.read_frame().await.unwrap(), b"first");
+        assert_eq!(events.recv().await, Some(ConnectionEvent::Connected));
+        // The second writer only attaches once the reader has seen the first one leave
+        let writer = guard.writer();
+        let (frame, event) = tokio::join!(reader.read_frame(), async {
+            let event = events.recv().await;
+            writer.async_write_frame(b"second").await.unwrap();
+            event
+        });
+        assert_eq!(frame.unwrap(), b"second");
+        assert_eq!(event, Some(ConnectionEvent::Disconnected));
+        assert_eq!(events.recv().await, Some(ConnectionEvent::Connected));
+        Ok(())
+    }
+
+    #[cfg(all(target_os = "linux", feature = "metrics"))]
+    #[tokio::test]
+    async fn metrics_count_traffic() -> io::Result<()> {
+        let pipe = super::Pipe::new("/tmp/test_pipe_105");
+        let (mut reader, mut writer) = pipe.open_pair().unwrap();
+        for data in [&b"one"[..], b"two", b"three"] {
+            let frame = crate::frame::encode_versioned(data).unwrap();
+            writer.write(&frame).await.unwrap();
+            assert_eq!(reader.read_frame().await.unwrap(), data);
+        }
+        let written = writer.metrics_snapshot();
+        assert_eq!(written.bytes_written, 8 + 8 + 10);
+        assert_eq!(written.write_errors, 0);
+        let read = reader.metrics_snapshot();
+        assert_eq!(read.bytes_read, 8 + 8 + 10);
+        assert_eq!((read.read_errors, read.reopens), (0, 0));
+        // With the only reader gone the next write fails with EPIPE
+        drop(reader);
+        assert!(writer.write(b"lost").await.is_err());
+        let after = writer.metrics_snapshot();
+        assert_eq!(after.write_errors, 1);
+        assert_eq!(after.bytes_written, written.bytes_written);
+        Ok(pipe.delete().await?)
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn errors_carry_path() -> io::Result<()> {
+        use super::PipeError;
+        use std::path::Path;
+        let missing = super::Pipe::new("/tmp/test_pipe_106");
+        match missing.reader().read_exact(&mut [0u8; 4]) {
+            Err(PipeError::PathIo { action, path, .. }) => {
+                assert_eq!(
+                    (action, path.as_path()),
+                    ("open", Path::new("/tmp/test_pipe_106"))
+                )
+            }
+            other => panic!("expected PathIo, got {:?}", other),
+        }
+        match missing.writer().write_partial(b"data") {
+            Err(err @ PipeError::PathIo { .. }) => {
+                assert!(err.to_string().contains("/tmp/test_pipe_106"))
+            }
+            other => panic!("expected PathIo, got {:?}", other),
+        }
+        match missing
+            .reader()
+            .open_with(super::OpenMode::BlockingOnce)
+            .await
+        {
+            Err(PipeError::PathIo { action, path, .. }) => {
+                assert_eq!(
+                    (action, path.as_path()),
+                    ("open", Path::new("/tmp/test_pipe_106"))
+                )
+            }
+            Err(other) => panic!("expected PathIo, got {:?}", other),
+            Ok(_) => panic!("opened a missing pipe"),
+        }
+        let pipe = super::Pipe::new("/tmp/test_pipe_107");
+        let guard = pipe.create_temp_guard().unwrap();
+        let writer = guard.writer();
+        // The header is cut short, so the read itself fails rather than the open
+        let t1 = task::spawn(async move { writer.async_write(&[1, 0]).await });
+        match guard.reader().async_read_frame().await {
+            Err(PipeError::PathIo { action, source, .. }) => {
+                assert_eq!(action, "read from");
+                assert_eq!(source.kind(), io::ErrorKind::UnexpectedEof);
+            }
+            other => panic!("expected PathIo, got {:?}", other),
+        }
+        t1.await?.unwrap();
+        Ok(())
+    }
+}
+
+/// The tests above that only need the read, write and delete calls, run on async-std
+#[cfg(all(test, feature = "async-std", not(feature = "async")))]
+mod async_std_tests {
+    use async_std::task;
+    use std::io;
+
+    #[test]
+    fn ensure_on_write() -> io::Result<()> {
+        task::block_on(async {
+            let pipe = super::Pipe::new("/tmp/test_pipe_async_std_1");
+            pipe.ensure_exists().unwrap();
+            let writer = pipe.writer();
+            let reader = pipe.reader();
+            let data_to_send = "Hello pipe";
+            let t1 = task::spawn(async move {
+                writer
+                    .pipe_exists()
+                    .unwrap()
+                    .async_write(data_to_send.as_bytes())
+                    .await
+            });
+            let t2 = task::spawn(async move { reader.async_read().await });
+            t1.await.unwrap();
+            assert_eq!(t2.await.unwrap(), data_to_send.as_bytes());
+            Ok(pipe.delete().await?)
+        })
+    }
+
+    #[test]
+    fn write_and_read_async() -> io::Result<()> {
+        task::block_on(async {
+            let pipe = super::Pipe::new("/tmp/test_pipe_async_std_2");
+            pipe.ensure_exists().unwrap();
+            let writer = pipe.writer();
+            let reader = pipe.reader();
+            let data_to_send = "Hello pipe";
+            let t1 = task::spawn(async move { writer.async_write_str(data_to_send).await });
+            let t2 = task::spawn(async move { reader.async_read_str().await });
+            t1.await.unwrap();
+            assert_eq!(t2.await.unwrap(), data_to_send);
+            Ok(pipe.delete().await?)
+        })
+    }
+
+    #[test]
+    fn write_line_round_trip() -> io::Result<()> {
+        task::block_on(async {
+            let pipe = super::Pipe::new("/tmp/test_pipe_async_std_3");
+            let guard = pipe.create_temp_guard().unwrap();
+            let writer = guard.writer();
+            let reader = guard.reader();
+            let t1 = task::spawn(async move { writer.async_write_line("single").await });
+            assert_eq!(reader.async_read_str().await.unwrap(), "single\n");
+            t1.await.unwrap();
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn try_delete_twice() -> io::Result<()> {
+        task::block_on(async {
+            let pipe = super::Pipe::new("/tmp/test_pipe_async_std_4");
+            pipe.ensure_exists().unwrap();
+            assert!(pipe.try_delete().await.unwrap());
+            assert!(!pipe.try_delete().await.unwrap());
+            pipe.ensure_exists().unwrap();
+            pipe.force_delete().await?;
+            Ok(())
+        })
+    }
 }
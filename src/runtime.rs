@@ -0,0 +1,57 @@
+//! The async runtime behind the plain read, write and delete calls
+//! tokio with the `async` feature, otherwise async-std with the `async-std` feature. Everything
+//! built on readiness (streams, connected writers, timeouts) is only available with tokio
+use std::fs::Metadata;
+use std::io;
+use std::path::Path;
+
+#[cfg(feature = "async")]
+pub(crate) use tokio::{fs::File, io::AsyncWriteExt as WriteExt};
+
+#[cfg(all(feature = "async-std", not(feature = "async")))]
+pub(crate) use async_std::{fs::File, io::WriteExt};
+
+#[cfg(feature = "async")]
+mod fs {
+    pub(super) use tokio::fs::*;
+
+    pub(super) fn path(path: &std::path::Path) -> &std::path::Path {
+        path
+    }
+}
+
+#[cfg(all(feature = "async-std", not(feature = "async")))]
+mod fs {
+    pub(super) use async_std::fs::*;
+
+    /// async-std has a `Path` of its own, it wraps the same `OsStr`
+    pub(super) fn path(path: &std::path::Path) -> &async_std::path::Path {
+        path.into()
+    }
+}
+
+/// Read everything until the last writer closes the pipe
+pub(crate) async fn read(path: &Path) -> io::Result<Vec<u8>> {
+    fs::read(fs::path(path)).await
+}
+
+/// Open the write side, waiting until a reader has the pipe open
+pub(crate) async fn open_write(path: &Path) -> io::Result<File> {
+    fs::OpenOptions::new()
+        .write(true)
+        .create(false)
+        .open(fs::path(path))
+        .await
+}
+
+pub(crate) async fn metadata(path: &Path) -> io::Result<Metadata> {
+    fs::metadata(fs::path(path)).await
+}
+
+pub(crate) async fn symlink_metadata(path: &Path) -> io::Result<Metadata> {
+    fs::symlink_metadata(fs::path(path)).await
+}
+
+pub(crate) async fn remove_file(path: &Path) -> io::Result<()> {
+    fs::remove_file(fs::path(path)).await
+}
@@ -0,0 +1,96 @@
+use crate::error::Result;
+use crate::pipe::Reader;
+use crate::stream::PipeReadStream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+
+/// Upper bound of bytes handed out by a single `PipeSelector::next`
+const CHUNK_SIZE: usize = 4096;
+
+struct Source {
+    reader: Reader,
+    stream: PipeReadStream,
+}
+
+/// Waits on several named pipes at once from a single task
+/// When the last writer of a pipe disconnects the pipe is reopened, so it keeps being watched
+pub struct PipeSelector {
+    sources: Vec<Source>,
+    /// Index polled first, rotated so a busy pipe can't starve the others
+    start: usize,
+}
+
+impl PipeSelector {
+    /// Open every reader without blocking, must be called from within a tokio runtime
+    pub fn new<I: IntoIterator<Item = Reader>>(readers: I) -> Result<Self> {
+        let sources = readers
+            .into_iter()
+            .map(|reader| {
+                let stream = reader.open_stream()?;
+                Ok(Source { reader, stream })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { sources, start: 0 })
+    }
+    /// Wait until one of the pipes has data, and return its index with the bytes read
+    /// Never resolves if the selector has no pipes
+    pub async fn next(&mut self) -> (usize, Result<Vec<u8>>) {
+        let mut buffer = vec![0u8; CHUNK_SIZE];
+        futures::future::poll_fn(|cx| self.poll_next(cx, &mut buffer)).await
+    }
+
+    fn poll_next(
+        &mut self,
+        cx: &mut Context<'_>,
+        buffer: &mut [u8],
+    ) -> Poll<(usize, Result<Vec<u8>>)> {
+        let count = self.sources.len();
+        for offset in 0..count {
+            let index = (self.start + offset) % count;
+            let source = &mut self.sources[index];
+            loop {
+                let mut read_buf = ReadBuf::new(buffer);
+                match Pin::new(&mut source.stream).poll_read(cx, &mut read_buf) {
+                    Poll::Pending => break,
+                    Poll::Ready(Err(err)) => {
+                        self.start = (index + 1) % count;
+                        return Poll::Ready((index, Err(err.into())));
+                    }
+                    Poll::Ready(Ok(())) if read_buf.filled().is_empty() => {
+                        // The last writer went away, reopen so the next writer is seen. The new
+                        // stream is opened before the old one is dropped to keep pending data
+                        match source.reader.open_stream() {
+                            Ok(stream) => source.stream = stream,
+                            Err(err) => return Poll::Ready((index, Err(err))),
+                        }
+                    }
+                    Poll::Ready(Ok(())) => {
+                        self.start = (index + 1) % count;
+                        return Poll::Ready((index, Ok(read_buf.filled().to_vec())));
+                    }
+                }
+            }
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io;
+
+    #[tokio::test]
+    async fn selects_pipe_with_data() -> io::Result<()> {
+        let first = crate::pipe::Pipe::new("/tmp/test_pipe_30");
+        let second = crate::pipe::Pipe::new("/tmp/test_pipe_31");
+        let first = first.create_temp_guard().unwrap();
+        let second = second.create_temp_guard().unwrap();
+        let mut selector = super::PipeSelector::new([first.reader(), second.reader()]).unwrap();
+        second.writer().async_write(b"Hello pipe").await.unwrap();
+        let (index, read_result) = selector.next().await;
+        assert_eq!(index, 1);
+        assert_eq!(read_result.unwrap(), b"Hello pipe");
+        Ok(())
+    }
+}
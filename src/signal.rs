@@ -0,0 +1,14 @@
+use nix::sys::signal::{signal, SigHandler, Signal};
+use std::sync::Once;
+
+static IGNORE_SIGPIPE: Once = Once::new();
+
+/// Ignore SIGPIPE for the whole process, so writing to a pipe without readers fails with `EPIPE`
+/// instead of killing the process. Only the first call does anything.
+/// Called automatically the first time a `Writer` is created
+pub fn ignore_sigpipe() {
+    IGNORE_SIGPIPE.call_once(|| {
+        // Safe because SIG_IGN doesn't run any code in signal context
+        let _ = unsafe { signal(Signal::SIGPIPE, SigHandler::SigIgn) };
+    });
+}
@@ -0,0 +1,455 @@
+use crate::error::Result;
+#[cfg(target_os = "linux")]
+use crate::frame;
+#[cfg(feature = "metrics")]
+use crate::metrics::{Counters, PipeMetrics};
+use crate::pipe::set_nonblocking;
+use futures::{task::AtomicWaker, Stream};
+use nix::fcntl::OFlag;
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::{Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use tokio::io::{self, unix::AsyncFd, AsyncBufReadExt, AsyncRead, AsyncWrite, BufReader, ReadBuf};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// A streaming read handle on a Unix named pipe
+/// The descriptor stays open, so a writer can keep sending data over time
+pub struct PipeReadStream {
+    inner: AsyncFd<File>,
+    watch: Option<Box<Watch>>,
+    #[cfg(feature = "metrics")]
+    metrics: Counters,
+}
+
+/// A writer attaching to or leaving a pipe watched by `Reader::open_persistent_with_events`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// The first data arrived after the pipe was idle (no writer since the last EOF)
+    Connected,
+    /// The last attached writer closed the pipe
+    Disconnected,
+}
+
+/// Reopens the pipe at EOF instead of ending the stream and reports the transitions
+struct Watch {
+    path: Arc<Path>,
+    connected: bool,
+    events: UnboundedSender<ConnectionEvent>,
+}
+
+impl PipeReadStream {
+    /// Open the named pipe for reading without blocking and register it with the tokio reactor
+    pub(crate) fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(OFlag::O_NONBLOCK.bits())
+            .open(path)?;
+        Self::wrap(file)
+    }
+    /// Register an open descriptor with the tokio reactor
+    fn wrap(file: File) -> io::Result<Self> {
+        Ok(Self {
+            inner: AsyncFd::new(file)?,
+            watch: None,
+            #[cfg(feature = "metrics")]
+            metrics: Counters::default(),
+        })
+    }
+    /// Wrap an already open read side, e.g. an inherited descriptor, without reopening by path
+    /// The stream takes ownership and closes it on drop, must be called from within a tokio runtime
+    pub fn from_file(file: File) -> io::Result<Self> {
+        set_nonblocking(file.as_raw_fd(), true)?;
+        Self::wrap(file)
+    }
+    /// Open the named pipe for reading and writing, so the read side never sees EOF
+    /// POSIX leaves `O_RDWR` on a FIFO undefined, Linux supports it
+    #[cfg(target_os = "linux")]
+    pub(crate) fn open_persistent<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(OFlag::O_NONBLOCK.bits())
+            .open(path)?;
+        Self::wrap(file)
+    }
+    /// Open the named pipe read only and reopen it whenever the last writer leaves
+    /// Every EOF becomes a `Disconnected` event on `events` instead of ending the stream
+    #[cfg(target_os = "linux")]
+    pub(crate) fn open_watched(
+        path: Arc<Path>,
+        events: UnboundedSender<ConnectionEvent>,
+    ) -> io::Result<Self> {
+        let mut stream = Self::open(&path)?;
+        stream.watch = Some(Box::new(Watch {
+            path,
+            connected: false,
+            events,
+        }));
+        Ok(stream)
+    }
+    /// Wait until a read won't block, without reading anything
+    /// Resolves when data is buffered, or with nothing buffered once the last writer has left
+    pub async fn readable(&self) -> io::Result<()> {
+        // Readiness stays set, so the next read goes straight to the descriptor
+        let _guard = self.inner.readable().await?;
+        Ok(())
+    }
+}
+
+/// Takes ownership of the descriptor, see `PipeReadStream::from_file`
+/// Panics if called outside of a tokio runtime
+impl FromRawFd for PipeReadStream {
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self::from_file(File::from_raw_fd(fd)).expect("failed to register pipe descriptor")
+    }
+}
+
+/// The descriptor is owned by the stream, callers must not close it
+impl AsRawFd for PipeReadStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl AsyncRead for PipeReadStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            let mut guard = ready!(this.inner.poll_read_ready(cx))?;
+            let unfilled = buf.initialize_unfilled();
+            let wants_data = !unfilled.is_empty();
+            match guard.try_io(|inner| inner.get_ref().read(unfilled)) {
+                Ok(Ok(len)) => {
+                    #[cfg(feature = "metrics")]
+                    this.metrics.read(len);
+                    let watch = match &mut this.watch {
+                        Some(watch) => watch,
+                        None => {
+                            buf.advance(len);
+                            return Poll::Ready(Ok(()));
+                        }
+                    };
+                    if len > 0 || !wants_data {
+                        if !watch.connected && len > 0 {
+                            watch.connected = true;
+                            let _ = watch.events.send(ConnectionEvent::Connected);
+                        }
+                        buf.advance(len);
+                        return Poll::Ready(Ok(()));
+                    }
+                    // Open the new descriptor before the old one closes, so the pipe always
+                    // has a reader and a writer showing up in between doesn't fail with ENXIO
+                    this.inner = AsyncFd::new(
+                        OpenOptions::new()
+                            .read(true)
+                            .custom_flags(OFlag::O_NONBLOCK.bits())
+                            .open(&watch.path)?,
+                    )?;
+                    #[cfg(feature = "metrics")]
+                    this.metrics.reopened();
+                    // A writer that left without writing anything still came and went
+                    if !watch.connected {
+                        let _ = watch.events.send(ConnectionEvent::Connected);
+                    }
+                    watch.connected = false;
+                    let _ = watch.events.send(ConnectionEvent::Disconnected);
+                }
+                Ok(Err(err)) => {
+                    #[cfg(feature = "metrics")]
+                    this.metrics.read_error();
+                    return Poll::Ready(Err(err));
+                }
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+/// A `Stream` of lines read from a Unix named pipe
+/// A final line without a trailing `\n` is still yielded at EOF, invalid UTF-8 yields an `InvalidData` error
+pub struct PipeLines {
+    inner: io::Lines<BufReader<PipeReadStream>>,
+    shutdown: Option<Arc<Shutdown>>,
+}
+
+impl PipeLines {
+    pub(crate) fn new(reader: BufReader<PipeReadStream>) -> Self {
+        Self {
+            inner: reader.lines(),
+            shutdown: None,
+        }
+    }
+}
+
+impl Stream for PipeLines {
+    type Item = io::Result<String>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(shutdown) = &self.shutdown {
+            if shutdown.poll(cx).is_ready() {
+                return Poll::Ready(None);
+            }
+        }
+        Pin::new(&mut self.inner)
+            .poll_next_line(cx)
+            .map(|line| line.transpose())
+    }
+}
+
+/// One record read by `RecordReader::read_until`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Record {
+    /// The bytes up to the delimiter
+    Complete(Vec<u8>),
+    /// The writer closed the pipe before a delimiter arrived, holds whatever was read until then
+    Eof(Vec<u8>),
+}
+
+/// Reads delimiter-separated records, keeping the pipe open between them
+pub struct RecordReader {
+    inner: BufReader<PipeReadStream>,
+}
+
+impl RecordReader {
+    pub(crate) fn new(stream: PipeReadStream) -> Self {
+        Self {
+            inner: BufReader::new(stream),
+        }
+    }
+    /// Read the next record ending in `delim`, with the delimiter kept if `include_delim` is set
+    /// Bytes after the delimiter stay buffered for the next call
+    pub async fn read_until(&mut self, delim: u8, include_delim: bool) -> Result<Record> {
+        let mut record = Vec::new();
+        self.inner.read_until(delim, &mut record).await?;
+        if record.last() == Some(&delim) {
+            if !include_delim {
+                record.pop();
+            }
+            Ok(Record::Complete(record))
+        } else {
+            Ok(Record::Eof(record))
+        }
+    }
+}
+
+/// Shared between a `PersistentReader` and its `ShutdownHandle`s
+#[derive(Default)]
+struct Shutdown {
+    triggered: AtomicBool,
+    waker: AtomicWaker,
+}
+
+impl Shutdown {
+    fn poll(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.triggered.load(Ordering::Acquire) {
+            return Poll::Ready(());
+        }
+        self.waker.register(cx.waker());
+        // Checked again in case the handle fired before the waker was registered
+        if self.triggered.load(Ordering::Acquire) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+    /// Run `read` unless shutdown fires first, which wins even if both are ready
+    async fn guard<F: Future<Output = Result<Vec<u8>>>>(&self, read: F) -> Result<Vec<u8>> {
+        tokio::select! {
+            biased;
+            _ = futures::future::poll_fn(|cx| self.poll(cx)) => Err(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                "persistent reader was shut down",
+            )
+            .into()),
+            frame = read => frame,
+        }
+    }
+}
+
+/// Stops a `PersistentReader`, or the line stream made from it, from another task
+#[cfg(target_os = "linux")]
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    inner: Arc<Shutdown>,
+}
+
+#[cfg(target_os = "linux")]
+impl ShutdownHandle {
+    /// Wake the reader and make it stop, even while it waits for data
+    /// The line stream ends with `None` and `read_frame` fails with `ConnectionAborted`
+    pub fn shutdown(&self) {
+        self.inner.triggered.store(true, Ordering::Release);
+        self.inner.waker.wake();
+    }
+    /// Check if `shutdown` has been called
+    pub fn is_shutdown(&self) -> bool {
+        self.inner.triggered.load(Ordering::Acquire)
+    }
+}
+
+/// A reader that outlives its writers
+/// It holds a write descriptor on the pipe itself, so writers may come and go without an EOF
+#[cfg(target_os = "linux")]
+pub struct PersistentReader {
+    inner: BufReader<PipeReadStream>,
+    shutdown: Arc<Shutdown>,
+}
+
+#[cfg(target_os = "linux")]
+impl PersistentReader {
+    pub(crate) fn new(stream: PipeReadStream) -> Self {
+        Self {
+            inner: BufReader::new(stream),
+            shutdown: Arc::default(),
+        }
+    }
+    /// Get a handle that stops this reader from another task
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle {
+            inner: self.shutdown.clone(),
+        }
+    }
+    /// Read a single versioned frame written by `Writer::write_frame`
+    /// Fails with `ConnectionAborted` once the reader has been shut down
+    pub async fn read_frame(&mut self) -> Result<Vec<u8>> {
+        let frame = frame::async_read_versioned(&mut self.inner);
+        self.shutdown.guard(frame).await
+    }
+    /// Read a single length-prefixed frame written by `Writer::write_frame_unversioned`
+    /// Fails with `ConnectionAborted` once the reader has been shut down
+    pub async fn read_frame_unversioned(&mut self) -> Result<Vec<u8>> {
+        let frame = frame::async_read(&mut self.inner);
+        self.shutdown.guard(frame).await
+    }
+    /// Counters of the bytes read, read errors and reopens of the pipe so far
+    /// Bytes count when they leave the kernel, frames still in the read buffer included
+    #[cfg(feature = "metrics")]
+    pub fn metrics_snapshot(&self) -> PipeMetrics {
+        self.inner.get_ref().metrics.snapshot()
+    }
+    /// Turn the reader into a stream of lines
+    /// It only ends once the reader is shut down through a `ShutdownHandle`
+    pub fn lines(self) -> PipeLines {
+        let mut lines = PipeLines::new(self.inner);
+        lines.shutdown = Some(self.shutdown);
+        lines
+    }
+}
+
+/// The descriptor is owned by the reader, callers must not close it
+#[cfg(target_os = "linux")]
+impl AsRawFd for PersistentReader {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.get_ref().as_raw_fd()
+    }
+}
+
+/// A streaming write handle on a Unix named pipe
+/// The descriptor stays open across writes until the stream is shut down or dropped
+pub struct PipeWriteStream {
+    inner: Option<AsyncFd<File>>,
+}
+
+impl PipeWriteStream {
+    /// Open the named pipe for writing without blocking and register it with the tokio reactor
+    /// Fails with `ENXIO` if no reader has the pipe open
+    pub(crate) fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .write(true)
+            .custom_flags(OFlag::O_NONBLOCK.bits())
+            .open(path)?;
+        Ok(Self {
+            inner: Some(AsyncFd::new(file)?),
+        })
+    }
+    /// Wrap an already open write side, e.g. an inherited descriptor, without reopening by path
+    /// The stream takes ownership and closes it on drop, must be called from within a tokio runtime
+    pub fn from_file(file: File) -> io::Result<Self> {
+        set_nonblocking(file.as_raw_fd(), true)?;
+        Ok(Self {
+            inner: Some(AsyncFd::new(file)?),
+        })
+    }
+
+    fn fd(&self) -> io::Result<&AsyncFd<File>> {
+        self.inner
+            .as_ref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "pipe stream is shut down"))
+    }
+}
+
+/// Takes ownership of the descriptor, see `PipeWriteStream::from_file`
+/// Panics if called outside of a tokio runtime
+impl FromRawFd for PipeWriteStream {
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self::from_file(File::from_raw_fd(fd)).expect("failed to register pipe descriptor")
+    }
+}
+
+/// The descriptor is owned by the stream, callers must not close it
+/// Returns `-1` once the stream has been shut down and the descriptor closed
+impl AsRawFd for PipeWriteStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_ref().map_or(-1, |inner| inner.as_raw_fd())
+    }
+}
+
+impl AsyncWrite for PipeWriteStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let inner = self.fd()?;
+        loop {
+            let mut guard = ready!(inner.poll_write_ready(cx))?;
+            match guard.try_io(|inner| inner.get_ref().write(buf)) {
+                Ok(result) => return Poll::Ready(result),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    /// Hand all slices to a single `writev`, the kernel may still accept only part of them
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[std::io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let inner = self.fd()?;
+        loop {
+            let mut guard = ready!(inner.poll_write_ready(cx))?;
+            match guard.try_io(|inner| inner.get_ref().write_vectored(bufs)) {
+                Ok(result) => return Poll::Ready(result),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    /// Writes go straight to the kernel, there is nothing buffered to flush
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    /// Close the descriptor so the reader observes EOF
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+        self.inner.take();
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,335 @@
+//! Windows named pipes exposed through the same `Pipe`/`Reader`/`Writer` surface as the Unix module
+//!
+//! Windows pipes live in the `\\.\pipe\` namespace instead of on the filesystem and only exist
+//! while a server end is open. The `Reader` acts as the server and creates a pipe instance for
+//! every read, the `Writer` connects to it as a client. Because of that `ensure_exists` and
+//! `delete` have nothing to do, and `exists` only reports `true` while a reader is waiting.
+//!
+//! The blocking reads drive the async ones on a runtime of their own on a separate thread. They
+//! work from inside a tokio runtime too, but block the calling worker until they finish.
+use crate::codec::Codec;
+use crate::error::Result;
+use crate::frame;
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Serialize};
+use std::ffi::{OsStr, OsString};
+use std::io::prelude::*;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
+use tokio::net::windows::named_pipe::{
+    ClientOptions, NamedPipeClient, NamedPipeServer, ServerOptions,
+};
+use tokio::time;
+
+/// Raw OS error returned while every instance of the pipe is connected
+const ERROR_PIPE_BUSY: i32 = 231;
+
+/// How long the writer waits before retrying to connect to a reader
+const CONNECT_RETRY: Duration = Duration::from_millis(10);
+
+/// Run a future to completion for the blocking variants of the methods
+/// The future gets a runtime of its own on a scoped thread, since `Runtime::block_on` panics on a
+/// thread that already drives one. Called from async code the method still blocks the calling
+/// worker like the Unix variants do, so prefer the `async_` methods there
+fn block_on<F>(future: F) -> std::io::Result<F::Output>
+where
+    F: std::future::Future + Send,
+    F::Output: Send,
+{
+    std::thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                Ok(tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()?
+                    .block_on(future))
+            })
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
+/// This object represents a name in the `\\.\pipe\` namespace
+#[derive(Clone, Debug)]
+pub struct Pipe {
+    inner: Arc<OsStr>,
+}
+
+impl Pipe {
+    /// The name must have the form `\\.\pipe\name`
+    pub fn new<T: Into<OsString>>(path: T) -> Self {
+        Self {
+            inner: Arc::from(path.into()),
+        }
+    }
+    /// Check if a reader currently serves this pipe
+    pub fn exists(&self) -> bool {
+        std::path::Path::new(&self.inner).exists()
+    }
+    /// Windows pipes are created by the reader on demand, so there is nothing to do here
+    pub fn ensure_exists(&self) -> Result<()> {
+        Ok(())
+    }
+    /// Windows pipes disappear with their last handle, so there is nothing to delete
+    pub async fn delete(self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Create a reader for this named pipe
+    pub fn reader(&self) -> Reader {
+        Reader::from_path(self)
+    }
+    /// Create a writer for this named pipe
+    pub fn writer(&self) -> Writer {
+        Writer::from_path(self)
+    }
+}
+
+impl std::fmt::Display for Pipe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.inner.to_string_lossy())
+    }
+}
+
+/// An util wrapper for reading from Windows named pipes
+#[derive(Debug)]
+pub struct Reader {
+    path: Pipe,
+}
+
+impl Reader {
+    /// Create a new reader sharing the path of the specific Pipe
+    pub fn from_path(source: &Pipe) -> Self {
+        Self {
+            path: source.clone(),
+        }
+    }
+    /// Kept for parity with Unix, Windows pipes always "exist" for a reader
+    pub fn pipe_exists(&self) -> Result<&Self> {
+        self.path.ensure_exists()?;
+        Ok(self)
+    }
+    /// Create a pipe instance and wait until a writer connects to it
+    async fn accept(&self) -> io::Result<NamedPipeServer> {
+        let server = ServerOptions::new()
+            .access_outbound(false)
+            .create(&self.path.inner)?;
+        server.connect().await?;
+        Ok(server)
+    }
+    /// Read all bytes from the pipe no async
+    pub fn read(&self) -> Result<Vec<u8>> {
+        block_on(self.async_read())?
+    }
+    /// Read all bytes from the pipe
+    /// The returned Future will resolve when something is written to the pipe
+    pub async fn async_read(&self) -> Result<Vec<u8>> {
+        let mut server = self.accept().await?;
+        let mut buffer = Vec::new();
+        match server.read_to_end(&mut buffer).await {
+            Ok(_) => Ok(buffer),
+            // The writer closing its handle is reported as a broken pipe, which is EOF for us
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(buffer),
+            Err(err) => Err(err.into()),
+        }
+    }
+    /// Read a String from the pipe no async
+    pub fn string(&self) -> Result<String> {
+        block_on(self.async_read_str())?
+    }
+    /// Reads a String from the pipe.
+    /// The returned Future will resolve when something is written to the pipe
+    pub async fn async_read_str(&self) -> Result<String> {
+        Ok(String::from_utf8(self.async_read().await?)?)
+    }
+    /// Read a single versioned frame written by `Writer::write_frame` no async
+    /// Fails with `PipeError::UnsupportedVersion` if the writer used another frame format
+    pub fn read_frame(&self) -> Result<Vec<u8>> {
+        block_on(self.async_read_frame())?
+    }
+    /// Read a single versioned frame written by `Writer::write_frame`
+    /// Fails with `PipeError::UnsupportedVersion` if the writer used another frame format
+    pub async fn async_read_frame(&self) -> Result<Vec<u8>> {
+        let mut server = self.accept().await?;
+        frame::async_read_versioned(&mut server).await
+    }
+    /// Read a single length-prefixed frame written by `Writer::write_frame_unversioned` no async
+    pub fn read_frame_unversioned(&self) -> Result<Vec<u8>> {
+        block_on(self.async_read_frame_unversioned())?
+    }
+    /// Read a single length-prefixed frame written by `Writer::write_frame_unversioned`
+    pub async fn async_read_frame_unversioned(&self) -> Result<Vec<u8>> {
+        let mut server = self.accept().await?;
+        frame::async_read(&mut server).await
+    }
+    /// Read a single frame written by `Writer::write_frame_checked` no async
+    pub fn read_frame_checked(&self) -> Result<Vec<u8>> {
+        block_on(self.async_read_frame_checked())?
+    }
+    /// Read a single frame written by `Writer::write_frame_checked`
+    pub async fn async_read_frame_checked(&self) -> Result<Vec<u8>> {
+        let mut server = self.accept().await?;
+        frame::async_read_checked(&mut server).await
+    }
+    /// Read one JSON message written by `Writer::write_json` no async
+    #[cfg(feature = "serde")]
+    pub fn read_json<T: DeserializeOwned>(&self) -> Result<T> {
+        Ok(serde_json::from_slice(&self.read_frame()?)?)
+    }
+    /// Read one JSON message written by `Writer::write_json`
+    #[cfg(feature = "serde")]
+    pub async fn async_read_json<T: DeserializeOwned>(&self) -> Result<T> {
+        Ok(serde_json::from_slice(&self.async_read_frame().await?)?)
+    }
+    /// Read everything one writer sends and decode it with `codec` no async
+    pub fn read_with<T, C: Codec<T>>(&self, codec: &C) -> Result<T> {
+        codec.decode(&self.read()?)
+    }
+    /// Read everything one writer sends and decode it with `codec`
+    pub async fn async_read_with<T, C: Codec<T>>(&self, codec: &C) -> Result<T> {
+        codec.decode(&self.async_read().await?)
+    }
+}
+
+/// An util wrapper for writing to Windows named pipes
+#[derive(Debug)]
+pub struct Writer {
+    path: Pipe,
+}
+
+impl Writer {
+    /// Connect to the reader, waiting until one has created a free pipe instance
+    async fn open(&self) -> io::Result<NamedPipeClient> {
+        loop {
+            match ClientOptions::new().read(false).open(&self.path.inner) {
+                Ok(client) => return Ok(client),
+                Err(err)
+                    if err.kind() == io::ErrorKind::NotFound
+                        || err.raw_os_error() == Some(ERROR_PIPE_BUSY) => {}
+                Err(err) => return Err(err),
+            }
+            time::sleep(CONNECT_RETRY).await;
+        }
+    }
+    async fn _write(&self, data: &[u8]) -> io::Result<()> {
+        let mut client = self.open().await?;
+        client.write_all(data).await
+    }
+    fn _write_blocking(&self, data: &[u8]) -> std::io::Result<()> {
+        loop {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .open(&*self.path.inner)
+            {
+                Ok(mut file) => return file.write_all(data),
+                Err(err)
+                    if err.kind() == io::ErrorKind::NotFound
+                        || err.raw_os_error() == Some(ERROR_PIPE_BUSY) => {}
+                Err(err) => return Err(err),
+            }
+            std::thread::sleep(CONNECT_RETRY);
+        }
+    }
+    pub fn from_path(source: &Pipe) -> Self {
+        Self {
+            path: source.clone(),
+        }
+    }
+    /// Kept for parity with Unix, Windows pipes always "exist" for a writer
+    pub fn pipe_exists(&self) -> Result<&Self> {
+        self.path.ensure_exists()?;
+        Ok(self)
+    }
+    /// Write byte data to the pipe no async
+    pub fn write(&self, data: &[u8]) -> Result<()> {
+        Ok(self._write_blocking(data)?)
+    }
+    /// Write byte data to the pipe
+    pub async fn async_write(&self, data: &[u8]) -> Result<()> {
+        Ok(self._write(data).await?)
+    }
+    /// Write &str data to the pipe no async
+    pub fn write_str(&self, data: String) -> Result<()> {
+        Ok(self._write_blocking(data.as_bytes())?)
+    }
+    /// Write &str data to the pipe
+    pub async fn async_write_str(&self, data: &str) -> Result<()> {
+        Ok(self._write(data.as_bytes()).await?)
+    }
+    /// Write data as one frame with a version byte and a 4-byte big-endian length header no async
+    pub fn write_frame(&self, data: &[u8]) -> Result<()> {
+        Ok(self._write_blocking(&frame::encode_versioned(data)?)?)
+    }
+    /// Write data as one frame with a version byte and a 4-byte big-endian length header
+    pub async fn async_write_frame(&self, data: &[u8]) -> Result<()> {
+        Ok(self._write(&frame::encode_versioned(data)?).await?)
+    }
+    /// Write data as one frame with only a 4-byte big-endian length header no async
+    /// For readers that predate versioned frames
+    pub fn write_frame_unversioned(&self, data: &[u8]) -> Result<()> {
+        Ok(self._write_blocking(&frame::encode(data)?)?)
+    }
+    /// Write data as one frame with only a 4-byte big-endian length header
+    /// For readers that predate versioned frames
+    pub async fn async_write_frame_unversioned(&self, data: &[u8]) -> Result<()> {
+        Ok(self._write(&frame::encode(data)?).await?)
+    }
+    /// Write data as one frame with a length header and a CRC32 of the payload no async
+    pub fn write_frame_checked(&self, data: &[u8]) -> Result<()> {
+        Ok(self._write_blocking(&frame::encode_checked(data)?)?)
+    }
+    /// Write data as one frame with a length header and a CRC32 of the payload
+    pub async fn async_write_frame_checked(&self, data: &[u8]) -> Result<()> {
+        Ok(self._write(&frame::encode_checked(data)?).await?)
+    }
+    /// Serialize a value to JSON and write it as one frame no async
+    #[cfg(feature = "serde")]
+    pub fn write_json<T: Serialize>(&self, value: &T) -> Result<()> {
+        self.write_frame(&serde_json::to_vec(value)?)
+    }
+    /// Serialize a value to JSON and write it as one frame
+    #[cfg(feature = "serde")]
+    pub async fn async_write_json<T: Serialize>(&self, value: &T) -> Result<()> {
+        self.async_write_frame(&serde_json::to_vec(value)?).await
+    }
+    /// Encode a value with `codec` and write it in one piece no async
+    pub fn write_with<T, C: Codec<T>>(&self, codec: &C, value: &T) -> Result<()> {
+        self.write(&codec.encode(value)?)
+    }
+    /// Encode a value with `codec` and write it in one piece
+    pub async fn async_write_with<T, C: Codec<T>>(&self, codec: &C, value: &T) -> Result<()> {
+        self.async_write(&codec.encode(value)?).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{io, task};
+
+    #[tokio::test]
+    async fn write_and_read_async() -> io::Result<()> {
+        let pipe = super::Pipe::new(r"\\.\pipe\test_pipe_0");
+        let writer = pipe.writer();
+        let reader = pipe.reader();
+        let data_to_send = "Hello pipe";
+        let t1 = task::spawn(async move { writer.async_write(data_to_send.as_bytes()).await });
+        let t2 = task::spawn(async move { reader.async_read_str().await });
+        t1.await?.unwrap();
+        let read_result = t2.await?.unwrap();
+        assert_eq!(read_result, data_to_send);
+        Ok(pipe.delete().await?)
+    }
+
+    #[tokio::test]
+    async fn blocking_read_inside_runtime() -> io::Result<()> {
+        let pipe = super::Pipe::new(r"\\.\pipe\test_pipe_1");
+        let writer = pipe.writer();
+        let t1 = std::thread::spawn(move || writer.write(b"Hello pipe"));
+        // Blocks this worker, but must not panic for being called inside a runtime
+        let read_result = pipe.reader().read().unwrap();
+        t1.join().unwrap().unwrap();
+        assert_eq!(read_result, b"Hello pipe");
+        Ok(())
+    }
+}